@@ -0,0 +1,56 @@
+use advent_of_code_2020::bench::{self, BenchOptions, Stage, Status};
+use advent_of_code_2020::{input_path, Part, DAYS};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::fs;
+use std::hint::black_box;
+use std::time::Duration;
+
+/// Parts that don't finish a single run within this time are left out, as
+/// criterion has no way of giving up on them.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn days(c: &mut Criterion) {
+    let probe = BenchOptions {
+        iterations: 1,
+        timeout: PROBE_TIMEOUT,
+    };
+
+    for day in DAYS {
+        let input = match fs::read_to_string(input_path(day.number)) {
+            Ok(input) => input,
+            Err(_) => continue,
+        };
+        let mut group = c.benchmark_group(format!("day{:02}", day.number));
+        group.sample_size(10);
+
+        for stage in Stage::ALL.iter() {
+            let status = bench::measure(day, *stage, &input, &probe).status;
+            if status != Status::Ok {
+                eprintln!("skipping day {} {}: {}", day.number, stage, status);
+                continue;
+            }
+
+            match stage {
+                Stage::Parse => {
+                    group.bench_function("parse", |b| b.iter(|| day.parse(black_box(&input))));
+                }
+                Stage::Part1 | Stage::Part2 => {
+                    let part = if *stage == Stage::Part1 {
+                        Part::One
+                    } else {
+                        Part::Two
+                    };
+                    let parsed = day.parse(&input).unwrap();
+                    group.bench_function(stage.to_string(), |b| {
+                        b.iter(|| parsed.solve(black_box(part)))
+                    });
+                }
+            }
+        }
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, days);
+criterion_main!(benches);
@@ -0,0 +1,359 @@
+use crate::{AocError, Day, Part};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Piece of a day that gets timed on its own.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Parse,
+    Part1,
+    Part2,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 3] = [Stage::Parse, Stage::Part1, Stage::Part2];
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Parse => write!(f, "parse"),
+            Stage::Part1 => write!(f, "part1"),
+            Stage::Part2 => write!(f, "part2"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Error,
+    Timeout,
+    Panicked,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Ok => write!(f, "ok"),
+            Status::Error => write!(f, "error"),
+            Status::Timeout => write!(f, "timeout"),
+            Status::Panicked => write!(f, "panicked"),
+        }
+    }
+}
+
+/// Timing of one stage of a day. Times are only meaningful when `status` is
+/// `Ok`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Measurement {
+    pub day: u32,
+    pub stage: Stage,
+    pub status: Status,
+    pub iterations: u32,
+    pub mean_ns: u64,
+    pub min_ns: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    pub measurements: Vec<Measurement>,
+}
+
+impl Report {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    pub fn get(&self, day: u32, stage: Stage) -> Option<&Measurement> {
+        self.measurements
+            .iter()
+            .find(|m| m.day == day && m.stage == stage)
+    }
+}
+
+pub struct BenchOptions {
+    /// Number of timed runs of every stage.
+    pub iterations: u32,
+    /// Time budget for all the runs of a single stage. Solvers can't be
+    /// interrupted, so a stage going over it is left running in the
+    /// background and reported as a timeout.
+    pub timeout: Duration,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        BenchOptions {
+            iterations: 10,
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Runs `f` on its own thread, giving up on it after `timeout`.
+pub fn run_with_timeout<T, F>(timeout: Duration, f: F) -> Result<T, Status>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // The receiver is gone if we already gave up waiting
+        let _ = sender.send(f());
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(v) => Ok(v),
+        Err(RecvTimeoutError::Timeout) => Err(Status::Timeout),
+        Err(RecvTimeoutError::Disconnected) => Err(Status::Panicked),
+    }
+}
+
+/// Times `stage` of `day` against `input`.
+pub fn measure(
+    day: &'static Day,
+    stage: Stage,
+    input: &str,
+    options: &BenchOptions,
+) -> Measurement {
+    let input = input.to_string();
+    let iterations = options.iterations.max(1);
+
+    let timed = run_with_timeout(options.timeout, move || {
+        let mut times = Vec::with_capacity(iterations as usize);
+        let parsed = day.parse(&input)?;
+        for _ in 0..iterations {
+            let start = Instant::now();
+            match stage {
+                Stage::Parse => {
+                    day.parse(&input)?;
+                }
+                Stage::Part1 => {
+                    parsed.solve(Part::One)?;
+                }
+                Stage::Part2 => {
+                    parsed.solve(Part::Two)?;
+                }
+            }
+            times.push(start.elapsed());
+        }
+        Ok::<_, AocError>(times)
+    });
+
+    let mut measurement = Measurement {
+        day: day.number,
+        stage,
+        status: Status::Ok,
+        iterations,
+        mean_ns: 0,
+        min_ns: 0,
+        error: None,
+    };
+    match timed {
+        Ok(Ok(times)) => {
+            let total: Duration = times.iter().sum();
+            measurement.mean_ns = (total / iterations).as_nanos() as u64;
+            measurement.min_ns = times.iter().min().unwrap().as_nanos() as u64;
+        }
+        Ok(Err(e)) => {
+            measurement.status = Status::Error;
+            measurement.error = Some(e.to_string());
+        }
+        Err(status) => measurement.status = status,
+    }
+
+    measurement
+}
+
+/// Times the generator and both parts of `day`.
+pub fn bench_day(day: &'static Day, input: &str, options: &BenchOptions) -> Vec<Measurement> {
+    Stage::ALL
+        .iter()
+        .map(|stage| measure(day, *stage, input, options))
+        .collect()
+}
+
+/// A stage that got slower than allowed, or that stopped working, compared
+/// to the baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+    pub day: u32,
+    pub stage: Stage,
+    pub baseline_ns: u64,
+    /// `None` when the stage no longer finishes successfully.
+    pub current_ns: Option<u64>,
+}
+
+impl Regression {
+    pub fn ratio(&self) -> Option<f64> {
+        self.current_ns
+            .map(|c| c as f64 / self.baseline_ns.max(1) as f64)
+    }
+}
+
+impl fmt::Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.current_ns, self.ratio()) {
+            (Some(current), Some(ratio)) => write!(
+                f,
+                "day {} {}: {} -> {} ({:.2}x)",
+                self.day,
+                self.stage,
+                format_ns(self.baseline_ns),
+                format_ns(current),
+                ratio
+            ),
+            _ => write!(
+                f,
+                "day {} {}: {} -> no longer succeeds",
+                self.day,
+                self.stage,
+                format_ns(self.baseline_ns)
+            ),
+        }
+    }
+}
+
+/// Stages whose mean time grew by more than `threshold` (0.1 being 10%)
+/// over the baseline. Stages missing from either report are ignored.
+pub fn compare(baseline: &Report, current: &Report, threshold: f64) -> Vec<Regression> {
+    let mut regressions = vec![];
+    for base in baseline
+        .measurements
+        .iter()
+        .filter(|m| m.status == Status::Ok)
+    {
+        let now = match current.get(base.day, base.stage) {
+            Some(m) => m,
+            None => continue,
+        };
+        let regression = Regression {
+            day: base.day,
+            stage: base.stage,
+            baseline_ns: base.mean_ns,
+            current_ns: if now.status == Status::Ok {
+                Some(now.mean_ns)
+            } else {
+                None
+            },
+        };
+        match regression.ratio() {
+            Some(ratio) if ratio <= 1.0 + threshold => (),
+            _ => regressions.push(regression),
+        }
+    }
+
+    regressions
+}
+
+/// Human readable duration, picking the unit from the magnitude.
+pub fn format_ns(ns: u64) -> String {
+    match ns {
+        0..=999 => format!("{}ns", ns),
+        1_000..=999_999 => format!("{:.1}µs", ns as f64 / 1e3),
+        1_000_000..=999_999_999 => format!("{:.1}ms", ns as f64 / 1e6),
+        _ => format!("{:.2}s", ns as f64 / 1e9),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(day: u32, stage: Stage, status: Status, mean_ns: u64) -> Measurement {
+        Measurement {
+            day,
+            stage,
+            status,
+            iterations: 1,
+            mean_ns,
+            min_ns: mean_ns,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_run_with_timeout() {
+        let timeout = Duration::from_millis(100);
+        assert_eq!(run_with_timeout(timeout, || 1), Ok(1));
+        assert_eq!(
+            run_with_timeout(timeout, || thread::sleep(Duration::from_secs(1))),
+            Err(Status::Timeout)
+        );
+        assert_eq!(
+            run_with_timeout(timeout, || -> u32 { panic!("boom") }),
+            Err(Status::Panicked)
+        );
+    }
+
+    #[test]
+    fn test_measure() {
+        let options = BenchOptions {
+            iterations: 3,
+            timeout: Duration::from_secs(10),
+        };
+        let day = crate::day(1).unwrap();
+        let m = measure(
+            day,
+            Stage::Part1,
+            "1721\n979\n366\n299\n675\n1456",
+            &options,
+        );
+        assert_eq!(m.status, Status::Ok);
+        assert_eq!(m.iterations, 3);
+        assert!(m.min_ns <= m.mean_ns);
+
+        let m = measure(day, Stage::Parse, "1721\nabc", &options);
+        assert_eq!(m.status, Status::Error);
+        assert!(m.error.is_some());
+    }
+
+    #[test]
+    fn test_compare() {
+        let baseline = Report {
+            measurements: vec![
+                measurement(1, Stage::Part1, Status::Ok, 1000),
+                measurement(1, Stage::Part2, Status::Ok, 1000),
+                measurement(2, Stage::Part1, Status::Ok, 1000),
+                measurement(3, Stage::Part1, Status::Timeout, 0),
+            ],
+        };
+        let current = Report {
+            measurements: vec![
+                measurement(1, Stage::Part1, Status::Ok, 1050),
+                measurement(1, Stage::Part2, Status::Ok, 2000),
+                measurement(2, Stage::Part1, Status::Panicked, 0),
+                measurement(3, Stage::Part1, Status::Ok, 5000),
+            ],
+        };
+        let regressions = compare(&baseline, &current, 0.1);
+        assert_eq!(regressions.len(), 2);
+        assert_eq!(regressions[0].ratio(), Some(2.0));
+        assert_eq!(regressions[1].current_ns, None);
+        assert_eq!(
+            regressions[0].to_string(),
+            "day 1 part2: 1.0µs -> 2.0µs (2.00x)"
+        );
+    }
+
+    #[test]
+    fn test_report_round_trip() {
+        let report = Report {
+            measurements: vec![measurement(5, Stage::Parse, Status::Ok, 42)],
+        };
+        let json = report.to_json().unwrap();
+        assert!(json.contains("\"stage\": \"parse\""));
+        assert_eq!(Report::from_json(&json).unwrap(), report);
+    }
+}
@@ -0,0 +1,704 @@
+use advent_of_code_2020::bench::{self, BenchOptions, Report, Status};
+use advent_of_code_2020::day02;
+use advent_of_code_2020::day03::{self, Goal, Legend, Moves, Slope};
+use advent_of_code_2020::day04::{self, Mode, Schema};
+use advent_of_code_2020::gen;
+use advent_of_code_2020::runner::RunResult;
+use advent_of_code_2020::runner::{self, Job};
+use advent_of_code_2020::verify::{self, Answers, Verdict};
+use advent_of_code_2020::{input_path, Part, DAYS};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::panic;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(name = "aoc2020", about = "Advent of Code 2020 solutions")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    /// Answers only, or a table with --all
+    Text,
+    /// A JSON object, or an array of them with --all
+    Json,
+    /// One JSON object per line, printed as soon as each part finishes
+    Ndjson,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Solve a single day and part, or the whole calendar with --all
+    Run {
+        #[arg(
+            long,
+            value_parser = clap::value_parser!(u32).range(1..=25),
+            required_unless_present = "all"
+        )]
+        day: Option<u32>,
+        #[arg(
+            long,
+            value_parser = clap::value_parser!(u32).range(1..=2),
+            required_unless_present = "all"
+        )]
+        part: Option<u32>,
+        /// Puzzle input file, or `-` for stdin. Defaults to input/2020/day<N>.txt
+        #[arg(long, conflicts_with = "all")]
+        input: Option<PathBuf>,
+        /// Run both parts of every day against input/2020/ concurrently
+        #[arg(long, conflicts_with_all = ["day", "part"])]
+        all: bool,
+        /// Worker threads used by --all. Defaults to the number of CPUs
+        #[arg(long, requires = "all")]
+        jobs: Option<usize>,
+        /// Seconds a part may take under --all or a machine-readable
+        /// format before being reported as a timeout. Defaults to 60
+        #[arg(long)]
+        timeout: Option<u64>,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+        /// Solve while reading the input line by line instead of loading it
+        /// whole, for the days that support it. Only prints text answers
+        #[arg(long, conflicts_with_all = ["all", "timeout"])]
+        stream: bool,
+    },
+    /// Time the generator and both parts of every day against input/2020/
+    Bench {
+        /// Only benchmark this day
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..=25))]
+        day: Option<u32>,
+        /// Timed runs of every generator and part
+        #[arg(long, default_value_t = 10)]
+        iterations: u32,
+        /// Seconds a generator or part may take over all its runs before
+        /// being reported as a timeout
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+        /// Where to write the JSON report
+        #[arg(long, default_value = "target/bench/report.json")]
+        output: PathBuf,
+        /// Previous report to compare against
+        #[arg(long)]
+        baseline: Option<PathBuf>,
+        /// Slowdown over the baseline, in percent, flagged as a regression
+        #[arg(long, default_value_t = 10.0)]
+        threshold: f64,
+    },
+    /// Generate a random puzzle input
+    Gen {
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..=25))]
+        day: u32,
+        /// Records in the input: lines, passports, answer groups, schedule
+        /// slots, starting numbers, grid side or nearby tickets depending on
+        /// the day
+        #[arg(long, default_value_t = 1000)]
+        size: usize,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Where to write the input. Defaults to stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// List the day 2 password rows failing the given policies
+    Passwords {
+        /// Policy to check, by name: count-range, positional-xor,
+        /// positional-and, regex:<pattern> or min-entropy:<bits>
+        #[arg(long, required = true)]
+        policy: Vec<String>,
+        /// Password rows, or `-` for stdin. Defaults to input/2020/day2.txt
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Convert day 2 password rows between the puzzle format, CSV and JSON
+    ConvertPasswords {
+        /// puzzle, csv or json
+        #[arg(long, default_value = "puzzle")]
+        from: day02::Format,
+        /// puzzle, csv or json
+        #[arg(long, default_value = "json")]
+        to: day02::Format,
+        /// Rows to convert, or `-` for stdin. Defaults to input/2020/day2.txt
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Where to write the rows. Defaults to stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Convert day 4 passport batches between the puzzle format, CSV and JSON
+    ConvertPassports {
+        /// puzzle, csv or json
+        #[arg(long, default_value = "puzzle")]
+        from: day04::Format,
+        /// puzzle, csv or json
+        #[arg(long, default_value = "json")]
+        to: day04::Format,
+        /// Passports to convert, or `-` for stdin. Defaults to input/2020/day4.txt
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Where to write the passports. Defaults to stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Find the day 3 slope hitting the fewest trees, or the most with --most
+    Slopes {
+        #[arg(long)]
+        most: bool,
+        /// Tiles of the map and what hitting them costs, instead of trees
+        /// costing 1
+        #[arg(long, default_value = ".=open:0,#=tree:1")]
+        legend: Legend,
+        /// Columns moved right on every step, at most
+        #[arg(long, default_value_t = 10)]
+        max_right: usize,
+        /// Denominator of fractional columns, at most. 1 only tries whole ones
+        #[arg(long, default_value_t = 1)]
+        max_per: usize,
+        /// Rows moved down on every step, at most
+        #[arg(long, default_value_t = 2)]
+        max_down: usize,
+        /// Toboggan map, or `-` for stdin. Defaults to input/2020/day3.txt
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Find the day 3 route from the top to the bottom hitting the fewest trees
+    Route {
+        /// right-down, king, or `down,right` offsets separated by `;`
+        #[arg(long, default_value = "right-down")]
+        moves: Moves,
+        /// Tiles of the map and what hitting them costs, instead of trees
+        /// costing 1
+        #[arg(long, default_value = ".=open:0,#=tree:1")]
+        legend: Legend,
+        /// Toboggan map, or `-` for stdin. Defaults to input/2020/day3.txt
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Draw the day 3 map with the tiles visited going down the given slopes
+    Render {
+        /// Slope as `right,down` or `right/per,down`. Can be repeated
+        #[arg(long, default_value = "3,1")]
+        slope: Vec<Slope>,
+        /// Draw an SVG with every slope in its own color instead of text
+        #[arg(long)]
+        svg: bool,
+        /// Tiles of the map and what hitting them costs, instead of trees
+        /// costing 1
+        #[arg(long, default_value = ".=open:0,#=tree:1")]
+        legend: Legend,
+        /// Toboggan map, or `-` for stdin. Defaults to input/2020/day3.txt
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Where to write the drawing. Defaults to stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Count the day 4 passports, or any other documents, valid under a schema
+    Validate {
+        /// TOML schema, or JSON when ending in .json. Defaults to the puzzle rules
+        #[arg(long)]
+        schema: Option<PathBuf>,
+        /// Print the schema as TOML instead of validating anything
+        #[arg(long)]
+        print_schema: bool,
+        /// List what's wrong with every failing document
+        #[arg(long)]
+        explain: bool,
+        /// Fail on malformed tokens, repeated or unknown keys instead of
+        /// skipping them
+        #[arg(long)]
+        strict: bool,
+        /// Blank line separated documents, or `-` for stdin. Defaults to
+        /// input/2020/day4.txt
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Check every day against the recorded answers
+    Verify {
+        /// Only verify this day
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..=25))]
+        day: Option<u32>,
+        #[arg(long, default_value = "answers/2020.toml")]
+        answers: PathBuf,
+        /// Seconds a part may take before counting as a failure
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+        /// Add the answers of parts without a recorded one to the answers file
+        #[arg(long)]
+        record: bool,
+    },
+}
+
+/// The `--timeout` of `run`, 60 seconds unless given.
+fn run_timeout(seconds: Option<u64>) -> Duration {
+    Duration::from_secs(seconds.unwrap_or(60))
+}
+
+fn read_input(day: u32, path: Option<PathBuf>) -> Result<String, Box<dyn Error>> {
+    match path {
+        Some(p) if p.as_os_str() == "-" => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            Ok(input)
+        }
+        Some(p) => Ok(fs::read_to_string(p)?),
+        None => Ok(fs::read_to_string(input_path(day))?),
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    match cli.command {
+        Command::Run {
+            all: true,
+            jobs,
+            timeout,
+            format,
+            ..
+        } => run_all(jobs, run_timeout(timeout), format)?,
+        Command::Run {
+            day,
+            part,
+            input,
+            format,
+            stream: true,
+            ..
+        } => {
+            if format != Format::Text {
+                return Err("--stream only prints text answers".into());
+            }
+            // clap makes both required unless --all is given
+            let (day, part) = (day.unwrap(), part.unwrap());
+            let d = advent_of_code_2020::day(day)
+                .ok_or_else(|| format!("day {} is not implemented", day))?;
+            let part = Part::from_number(part).unwrap();
+            let answer = match input {
+                Some(p) if p.as_os_str() == "-" => d.run_stream(&mut io::stdin().lock(), part)?,
+                Some(p) => d.run_stream(&mut BufReader::new(File::open(p)?), part)?,
+                None => d.run_stream(&mut BufReader::new(File::open(input_path(day))?), part)?,
+            };
+            println!("{}", answer);
+        }
+        Command::Run {
+            day,
+            part,
+            input,
+            timeout,
+            format,
+            ..
+        } => {
+            if format == Format::Text && timeout.is_some() {
+                return Err("--timeout needs --all or a machine-readable --format".into());
+            }
+            // clap makes both required unless --all is given
+            let (day, part) = (day.unwrap(), part.unwrap());
+            // Days without a solver have no input to read either
+            let d = advent_of_code_2020::day(day)
+                .ok_or_else(|| format!("day {} is not implemented", day))?;
+            let part = Part::from_number(part).unwrap();
+            let input = read_input(day, input)?;
+            if format == Format::Text {
+                println!("{}", d.run(&input, part)?);
+            } else {
+                let job = Job {
+                    day: d,
+                    part,
+                    input: Arc::new(input),
+                };
+                let result = runner::run_job(job, run_timeout(timeout));
+                print_json(&result, format)?;
+                if result.status != Status::Ok {
+                    process::exit(1);
+                }
+            }
+        }
+        Command::Bench {
+            day,
+            iterations,
+            timeout,
+            output,
+            baseline,
+            threshold,
+        } => {
+            let options = BenchOptions {
+                iterations,
+                timeout: Duration::from_secs(timeout),
+            };
+            let report = run_bench(day, &options)?;
+
+            if let Some(dir) = output.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&output, report.to_json()?)?;
+            println!("report written to {}", output.display());
+
+            if let Some(path) = baseline {
+                let baseline = Report::from_json(&fs::read_to_string(path)?)?;
+                let regressions = bench::compare(&baseline, &report, threshold / 100.0);
+                if !regressions.is_empty() {
+                    for r in &regressions {
+                        eprintln!("regression: {}", r);
+                    }
+                    return Err(format!("{} regression(s) found", regressions.len()).into());
+                }
+                println!("no regressions over {}%", threshold);
+            }
+        }
+        Command::Gen {
+            day,
+            size,
+            seed,
+            output,
+        } => {
+            let input = gen::generate(day, size, seed)?;
+            match output {
+                Some(path) => fs::write(path, input)?,
+                None => print!("{}", input),
+            }
+        }
+        Command::Passwords { policy, input } => {
+            let policies = policy
+                .iter()
+                .map(|name| day02::policy(name))
+                .collect::<Result<Vec<_>, _>>()?;
+            let rows = day02::parse_input_day2(read_input(2, input)?.trim_end_matches('\n'))?;
+            let failures = day02::report(&rows, &policies);
+            for failure in &failures {
+                println!("{}", failure);
+            }
+            println!("{} failure(s) over {} row(s)", failures.len(), rows.len());
+        }
+        Command::ConvertPasswords {
+            from,
+            to,
+            input,
+            output,
+        } => {
+            let rows = day02::import(&read_input(2, input)?, from)?;
+            let converted = day02::export(&rows, to)?;
+            match output {
+                Some(path) => fs::write(path, converted)?,
+                None => print!("{}", converted),
+            }
+        }
+        Command::ConvertPassports {
+            from,
+            to,
+            input,
+            output,
+        } => {
+            let passports = day04::import(&read_input(4, input)?, from)?;
+            let converted = day04::export(&passports, to)?;
+            match output {
+                Some(path) => fs::write(path, converted)?,
+                None => print!("{}", converted),
+            }
+        }
+        Command::Slopes {
+            most,
+            legend,
+            max_right,
+            max_per,
+            max_down,
+            input,
+        } => {
+            let map = day03::parse_map_with(read_input(3, input)?.trim_end_matches('\n'), legend)?;
+            let goal = if most { Goal::Most } else { Goal::Fewest };
+            let slopes = Slope::all(max_right, max_per, max_down);
+            match day03::best_slope(&map, slopes, goal)? {
+                Some((slope, _)) => println!("{}: {}", slope, map.breakdown(slope)?),
+                None => return Err("no slope within the bounds".into()),
+            }
+        }
+        Command::Route {
+            moves,
+            legend,
+            input,
+        } => {
+            let map = day03::parse_map_with(read_input(3, input)?.trim_end_matches('\n'), legend)?;
+            match day03::cheapest_route(&map, &moves)? {
+                Some(route) => {
+                    let tiles: Vec<String> = route
+                        .tiles
+                        .iter()
+                        .map(|(i, j)| format!("{},{}", i, j))
+                        .collect();
+                    println!("cost {} over {} tile(s)", route.cost, route.tiles.len());
+                    println!("{}", tiles.join(" "));
+                }
+                None => return Err("no route reaches the bottom with these moves".into()),
+            }
+        }
+        Command::Render {
+            slope,
+            svg,
+            legend,
+            input,
+            output,
+        } => {
+            let map = day03::parse_map_with(read_input(3, input)?.trim_end_matches('\n'), legend)?;
+            let drawing = if svg {
+                day03::render_svg(&map, &slope)?
+            } else {
+                let cells = slope
+                    .iter()
+                    .flat_map(|s| map.trace(*s).map(|(cell, _)| cell));
+                day03::render_text(&map, cells)
+            };
+            match output {
+                Some(path) => fs::write(path, drawing)?,
+                None => print!("{}", drawing),
+            }
+        }
+        Command::Validate {
+            schema,
+            print_schema,
+            explain,
+            strict,
+            input,
+        } => {
+            let schema = match schema {
+                Some(path) if path.extension().is_some_and(|e| e == "json") => {
+                    Schema::from_json(&fs::read_to_string(path)?)?
+                }
+                Some(path) => Schema::from_toml(&fs::read_to_string(path)?)?,
+                None => Schema::default(),
+            };
+            if print_schema {
+                print!("{}", schema.to_toml()?);
+                return Ok(());
+            }
+            let mode = if strict { Mode::Strict } else { Mode::Lenient };
+            let tokenized = day04::tokenize(&read_input(4, input)?, &schema, mode)?;
+            for issue in &tokenized.issues {
+                eprintln!("skipped: {}", issue);
+            }
+            let documents = tokenized.records;
+            if explain {
+                for (idx, document) in documents.iter().enumerate() {
+                    let violations: Vec<String> = schema
+                        .validate(document)
+                        .violations
+                        .iter()
+                        .map(|v| v.to_string())
+                        .collect();
+                    if !violations.is_empty() {
+                        println!("document {}: {}", idx + 1, violations.join("; "));
+                    }
+                }
+            }
+            println!("{}", day04::report(&schema, &documents));
+        }
+        Command::Verify {
+            day,
+            answers,
+            timeout,
+            record,
+        } => verify_days(day, &answers, Duration::from_secs(timeout), record)?,
+    }
+
+    Ok(())
+}
+
+fn run_bench(day: Option<u32>, options: &BenchOptions) -> Result<Report, Box<dyn Error>> {
+    let mut report = Report::default();
+    println!(
+        "{:>3}  {:<6}  {:<8}  {:>10}  {:>10}",
+        "day", "stage", "status", "mean", "min"
+    );
+    for d in DAYS
+        .iter()
+        .filter(|d| day.is_none() || day == Some(d.number))
+    {
+        let input = match fs::read_to_string(input_path(d.number)) {
+            Ok(input) => input,
+            Err(e) => {
+                eprintln!("skipping day {}: {}", d.number, e);
+                continue;
+            }
+        };
+        for m in bench::bench_day(d, &input, options) {
+            let (mean, min) = match m.status {
+                Status::Ok => (bench::format_ns(m.mean_ns), bench::format_ns(m.min_ns)),
+                _ => ("-".to_string(), "-".to_string()),
+            };
+            println!(
+                "{:>3}  {:<6}  {:<8}  {:>10}  {:>10}",
+                m.day,
+                m.stage.to_string(),
+                m.status.to_string(),
+                mean,
+                min
+            );
+            report.measurements.push(m);
+        }
+    }
+
+    Ok(report)
+}
+
+fn print_json(result: &RunResult, format: Format) -> serde_json::Result<()> {
+    if format == Format::Ndjson {
+        println!("{}", serde_json::to_string(result)?);
+    } else {
+        println!("{}", serde_json::to_string_pretty(result)?);
+    }
+
+    Ok(())
+}
+
+fn run_all(
+    workers: Option<usize>,
+    timeout: Duration,
+    format: Format,
+) -> Result<(), Box<dyn Error>> {
+    let mut jobs = vec![];
+    for d in DAYS {
+        let input = match fs::read_to_string(input_path(d.number)) {
+            Ok(input) => Arc::new(input),
+            Err(e) => {
+                eprintln!("skipping day {}: {}", d.number, e);
+                continue;
+            }
+        };
+        for part in Part::ALL.iter() {
+            jobs.push(Job {
+                day: d,
+                part: *part,
+                input: Arc::clone(&input),
+            });
+        }
+    }
+    let workers = workers
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1);
+
+    // Panics are reported in the results instead
+    panic::set_hook(Box::new(|_| {}));
+    let results = match format {
+        Format::Ndjson => {
+            let mut results = vec![];
+            for result in runner::run_each(jobs, workers, timeout) {
+                print_json(&result, format)?;
+                results.push(result);
+            }
+            results
+        }
+        _ => runner::run_all(jobs, workers, timeout),
+    };
+    let _ = panic::take_hook();
+
+    let failed = results.iter().filter(|r| r.status != Status::Ok).count();
+    match format {
+        Format::Text => print_table(&results),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&results)?),
+        Format::Ndjson => (),
+    }
+    if failed > 0 {
+        return Err(format!("{} part(s) did not produce an answer", failed).into());
+    }
+
+    Ok(())
+}
+
+fn print_table(results: &[RunResult]) {
+    println!(
+        "{:>3}  {:>4}  {:<20}  {:>10}  status",
+        "day", "part", "answer", "elapsed"
+    );
+    for r in results {
+        let answer = match (&r.answer, &r.error) {
+            (Some(answer), _) => answer.clone(),
+            (None, Some(e)) => e.clone(),
+            (None, None) => String::new(),
+        };
+        println!(
+            "{:>3}  {:>4}  {:<20}  {:>10}  {}",
+            r.day,
+            r.part.number(),
+            answer,
+            bench::format_ns(r.elapsed().as_nanos() as u64),
+            r.status
+        );
+    }
+}
+
+fn verify_days(
+    day: Option<u32>,
+    path: &PathBuf,
+    timeout: Duration,
+    record: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut answers = match fs::read_to_string(path) {
+        Ok(toml) => Answers::from_toml(&toml)?,
+        Err(e) if record && e.kind() == io::ErrorKind::NotFound => Answers::default(),
+        Err(e) => return Err(format!("{}: {}", path.display(), e).into()),
+    };
+
+    let mut failed = 0;
+    let mut recorded = 0;
+    println!("day  part  verdict  answer");
+    for d in DAYS
+        .iter()
+        .filter(|d| day.is_none() || day == Some(d.number))
+    {
+        let input = match fs::read_to_string(input_path(d.number)) {
+            Ok(input) => input,
+            Err(e) => {
+                eprintln!("skipping day {}: {}", d.number, e);
+                continue;
+            }
+        };
+        for check in verify::verify_day(d, &input, &answers, timeout) {
+            let detail = match (&check.verdict, &check.actual, &check.expected) {
+                (Verdict::Fail, Ok(actual), Some(expected)) => {
+                    format!("{} (expected {})", actual, expected)
+                }
+                (_, Ok(actual), _) => actual.clone(),
+                (_, Err(e), _) => e.clone(),
+            };
+            println!(
+                "{:>3}  {:>4}  {:<7}  {}",
+                check.day,
+                check.part.number(),
+                check.verdict.to_string(),
+                detail
+            );
+            match (check.verdict, check.actual) {
+                (Verdict::Fail, _) => failed += 1,
+                (Verdict::Missing, Ok(actual)) if record => {
+                    answers.set(check.day, check.part, actual);
+                    recorded += 1;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    if recorded > 0 {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, answers.to_toml())?;
+        println!("{} answer(s) recorded in {}", recorded, path.display());
+    }
+    if failed > 0 {
+        return Err(format!("{} part(s) failed verification", failed).into());
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run(Cli::parse()) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
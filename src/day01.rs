@@ -1,40 +1,331 @@
+use crate::error::parse_token;
+use crate::stream::records;
+use crate::{AocError, Solver, StreamSolver};
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::num::ParseIntError;
+use num_bigint::BigInt;
+use std::any;
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::io::BufRead;
+use std::str::FromStr;
 
+const DAY: u32 = 1;
+
+/// Number types the expense report can be solved with. Arithmetic is
+/// checked, so products that don't fit in the type are reported as an
+/// `AocError::Overflow` instead of wrapping around.
+pub trait Entry: Clone + Ord + Display + FromStr {
+    fn zero() -> Self;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_sub(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+}
+
+macro_rules! impl_entry {
+    ($($t:ty),*) => {
+        $(
+            impl Entry for $t {
+                fn zero() -> Self {
+                    0
+                }
+
+                fn checked_add(&self, other: &Self) -> Option<Self> {
+                    <$t>::checked_add(*self, *other)
+                }
+
+                fn checked_sub(&self, other: &Self) -> Option<Self> {
+                    <$t>::checked_sub(*self, *other)
+                }
+
+                fn checked_mul(&self, other: &Self) -> Option<Self> {
+                    <$t>::checked_mul(*self, *other)
+                }
+            }
+        )*
+    };
+}
+
+impl_entry!(i32, i64, i128);
+
+impl Entry for BigInt {
+    fn zero() -> Self {
+        BigInt::from(0)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        Some(self - other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+}
+
+fn overflow<T: Entry>(a: &T, op: char, b: &T) -> AocError {
+    AocError::overflow(
+        DAY,
+        format!(
+            "{} {} {} does not fit in {}",
+            a,
+            op,
+            b,
+            any::type_name::<T>()
+        ),
+    )
+}
+
+/// Parses one expense entry per line.
 #[aoc_generator(day1)]
-fn parse_input_day_1(input: &str) -> Result<Vec<i32>, ParseIntError> {
-    input.lines().map(|n| n.parse()).collect()
+pub fn parse_input_day_1(input: &str) -> Result<Vec<i64>, AocError> {
+    read_entries(input.as_bytes()).collect()
 }
 
+/// Parses the entries one line at a time out of `reader`.
+pub fn read_entries<T, R>(reader: R) -> impl Iterator<Item = Result<T, AocError>>
+where
+    T: Entry,
+    T::Err: Display,
+    R: BufRead,
+{
+    records(reader, |idx, n| parse_token(DAY, idx, n, n))
+}
+
+/// Product of the two entries that sum to 2020. Each entry is used once,
+/// where the original loops answered `1010 * 1010` for a single `1010`.
 #[aoc(day1, part1)]
-fn part1(_input: &[i32]) -> i32 {
-    for x in _input.iter() {
-        for y in _input.iter() {
-            if x + y == 2020 {
-                return x * y;
+pub fn part1(input: &[i64]) -> Result<i64, AocError> {
+    product_of(input, 2, 2020)?
+        .ok_or_else(|| AocError::no_solution(DAY, "no two entries sum to 2020"))
+}
+
+/// Product of the three entries that sum to 2020, each used once.
+#[aoc(day1, part2)]
+pub fn part2(input: &[i64]) -> Result<i64, AocError> {
+    product_of(input, 3, 2020)?
+        .ok_or_else(|| AocError::no_solution(DAY, "no three entries sum to 2020"))
+}
+
+/// Product of the first `k` entries found to sum to `target`, if any.
+pub fn product_of<T: Entry>(values: &[T], k: usize, target: T) -> Result<Option<T>, AocError> {
+    let combination = match k_sum(values, k, target).into_iter().next() {
+        Some(combination) => combination,
+        None => return Ok(None),
+    };
+    let mut product = values[combination[0]].clone();
+    for idx in &combination[1..] {
+        product = product
+            .checked_mul(&values[*idx])
+            .ok_or_else(|| overflow(&product, '*', &values[*idx]))?;
+    }
+
+    Ok(Some(product))
+}
+
+/// Every combination of `k` different indices of `values` whose values add
+/// up to `target`. Indices are ascending within a combination and the
+/// combinations are in lexicographic order.
+///
+/// Values are sorted once and the last two indices of each combination are
+/// found closing in from both ends, so this takes O(n^(k-1)) steps besides
+/// the ones needed to list the results. Sums are only compared with
+/// `target`, never computed, so extreme values can't overflow the search.
+pub fn k_sum<T: Entry>(values: &[T], k: usize, target: T) -> Vec<Vec<usize>> {
+    let mut sorted: Vec<(T, usize)> = values.iter().cloned().zip(0..).collect();
+    sorted.sort_unstable();
+
+    let mut combinations = vec![];
+    let mut chosen = vec![];
+    k_sum_from(&sorted, k, &target, &mut chosen, &mut combinations);
+    for combination in combinations.iter_mut() {
+        combination.sort_unstable();
+    }
+    combinations.sort_unstable();
+    combinations
+}
+
+/// How the sum of `terms` compares to `target`, even when it doesn't fit in
+/// `T`. Terms are added picking one of the sign bringing the running total
+/// back towards zero, which can't overflow, and once only terms of the same
+/// sign as the total are left the outcome is known.
+fn cmp_sum<'a, T: Entry + 'a>(terms: impl IntoIterator<Item = &'a T>, target: &T) -> Ordering {
+    let zero = T::zero();
+    let (mut positives, mut negatives): (Vec<&T>, Vec<&T>) = terms
+        .into_iter()
+        .filter(|t| **t != zero)
+        .partition(|t| **t > zero);
+    // Taking the target away counts against its own sign
+    let mut target = Some(target).filter(|t| **t != zero);
+    let mut total = zero.clone();
+    loop {
+        let step = if total >= zero {
+            match negatives.pop() {
+                Some(term) => total.checked_add(term),
+                None => match target.take_if(|t| **t > zero) {
+                    Some(t) => total.checked_sub(t),
+                    None => break,
+                },
+            }
+        } else {
+            match positives.pop() {
+                Some(term) => total.checked_add(term),
+                None => match target.take_if(|t| **t < zero) {
+                    Some(t) => total.checked_sub(t),
+                    None => break,
+                },
+            }
+        };
+        total = step.expect("terms of opposite signs don't overflow");
+    }
+
+    match total.cmp(&zero) {
+        Ordering::Equal if !positives.is_empty() || target.is_some() => Ordering::Greater,
+        ordering => ordering,
+    }
+}
+
+/// Values of the chosen entries followed by `more`.
+fn with<'a, T>(
+    chosen: &'a [&'a (T, usize)],
+    more: &'a [&'a (T, usize)],
+) -> impl Iterator<Item = &'a T> {
+    chosen.iter().chain(more).map(|(v, _)| v)
+}
+
+/// Adds to `found` every way of picking `k` of the `sorted` values adding
+/// up to `target` along with the ones already `chosen`.
+fn k_sum_from<'a, T: Entry>(
+    sorted: &'a [(T, usize)],
+    k: usize,
+    target: &T,
+    chosen: &mut Vec<&'a (T, usize)>,
+    found: &mut Vec<Vec<usize>>,
+) {
+    if sorted.len() < k {
+        return;
+    }
+    match k {
+        0 => {
+            if cmp_sum(with(chosen, &[]), target) == Ordering::Equal {
+                found.push(chosen.iter().map(|(_, idx)| *idx).collect());
+            }
+        }
+        1 => {
+            for entry in sorted {
+                if cmp_sum(with(chosen, &[entry]), target) == Ordering::Equal {
+                    chosen.push(entry);
+                    found.push(chosen.iter().map(|(_, idx)| *idx).collect());
+                    chosen.pop();
+                }
+            }
+        }
+        2 => pairs_from(sorted, target, chosen, found),
+        _ => {
+            for i in 0..=sorted.len() - k {
+                let rest = &sorted[i + 1..];
+                chosen.push(&sorted[i]);
+                // The smallest and largest sums still reachable from here
+                let lowest: Vec<&(T, usize)> = rest[..k - 1].iter().collect();
+                let highest: Vec<&(T, usize)> = rest[rest.len() - (k - 1)..].iter().collect();
+                if cmp_sum(with(chosen, &lowest), target) == Ordering::Greater {
+                    chosen.pop();
+                    break;
+                }
+                if cmp_sum(with(chosen, &highest), target) != Ordering::Less {
+                    k_sum_from(rest, k - 1, target, chosen, found);
+                }
+                chosen.pop();
             }
         }
     }
-    unreachable!();
 }
 
-#[aoc(day1, part2)]
-fn part2(_input: &[i32]) -> i32 {
-    for x in _input.iter() {
-        for y in _input.iter() {
-            for z in _input.iter() {
-                if x + y + z == 2020 {
-                    return x * y * z;
+/// Two pointers version of `k_sum_from` for pairs. Runs of equal values are
+/// expanded so every pair of indices is listed.
+fn pairs_from<'a, T: Entry>(
+    sorted: &'a [(T, usize)],
+    target: &T,
+    chosen: &[&'a (T, usize)],
+    found: &mut Vec<Vec<usize>>,
+) {
+    let indices = |i: usize, j: usize| {
+        let chosen = chosen.iter().map(|(_, idx)| *idx);
+        chosen.chain([sorted[i].1, sorted[j].1]).collect()
+    };
+    let (mut lo, mut hi) = (0, sorted.len().saturating_sub(1));
+    while lo < hi {
+        let (low, high) = (&sorted[lo].0, &sorted[hi].0);
+        match cmp_sum(with(chosen, &[&sorted[lo], &sorted[hi]]), target) {
+            Ordering::Less => lo += 1,
+            Ordering::Greater => hi -= 1,
+            Ordering::Equal if low == high => {
+                // Everything in between is the same value
+                for i in lo..=hi {
+                    for j in i + 1..=hi {
+                        found.push(indices(i, j));
+                    }
                 }
+                return;
+            }
+            Ordering::Equal => {
+                let lo_end = lo + sorted[lo..].iter().take_while(|(v, _)| v == low).count();
+                let hi_start = hi + 1
+                    - sorted[..=hi]
+                        .iter()
+                        .rev()
+                        .take_while(|(v, _)| v == high)
+                        .count();
+                for i in lo..lo_end {
+                    for j in hi_start..=hi {
+                        found.push(indices(i, j));
+                    }
+                }
+                lo = lo_end;
+                hi = hi_start - 1;
             }
         }
     }
-    unreachable!();
+}
+
+/// Solver for day 1, usable outside of cargo-aoc.
+pub struct Day01;
+
+impl Solver for Day01 {
+    type Input = Vec<i64>;
+    type Output = i64;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        parse_input_day_1(input)
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        part2(input)
+    }
+}
+
+// Any entry may pair up with any later one, so only the text is dropped
+impl StreamSolver for Day01 {
+    fn part1_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        part1(&read_entries(reader).collect::<Result<Vec<i64>, AocError>>()?)
+    }
+
+    fn part2_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        part2(&read_entries(reader).collect::<Result<Vec<i64>, AocError>>()?)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use itertools::Itertools;
+    use proptest::prelude::*;
 
     #[test]
     fn test_parse_input() {
@@ -48,13 +339,199 @@ mod tests {
     fn test_p1_e1() {
         let input = "1721\n979\n366\n299\n675\n1456";
         let input_vec = parse_input_day_1(input).unwrap();
-        assert_eq!(part1(&input_vec), 514579);
+        assert_eq!(part1(&input_vec).unwrap(), 514579);
     }
 
     #[test]
     fn test_p2_e1() {
         let input = "1721\n979\n366\n299\n675\n1456";
         let input_vec = parse_input_day_1(input).unwrap();
-        assert_eq!(part2(&input_vec), 241861950);
+        assert_eq!(part2(&input_vec).unwrap(), 241861950);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            parse_input_day_1("1721\n97x9"),
+            Err(AocError::parse(
+                1,
+                2,
+                1,
+                "97x9",
+                "invalid digit found in string"
+            ))
+        );
+        assert!(part1(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_k_sum() {
+        let input = [1721, 979, 366, 299, 675, 1456];
+        assert_eq!(k_sum(&input, 2, 2020), vec![vec![0, 3]]);
+        assert_eq!(k_sum(&input, 3, 2020), vec![vec![1, 2, 4]]);
+        assert_eq!(k_sum(&input, 1, 366), vec![vec![2]]);
+        assert_eq!(k_sum(&input, 0, 0), vec![Vec::<usize>::new()]);
+        assert!(k_sum(&input, 7, 5496).is_empty());
+
+        // An entry is never used twice
+        assert!(k_sum(&[1010], 2, 2020).is_empty());
+        assert_eq!(
+            k_sum(&[1010, 5, 1010, 1010], 2, 2020),
+            vec![vec![0, 2], vec![0, 3], vec![2, 3]]
+        );
+        assert_eq!(k_sum(&[3, 1, 2, 2, 1], 2, 4).len(), 3);
+        assert_eq!(k_sum(&[-5, 5, 0, 10, -10], 4, 0), vec![vec![0, 1, 3, 4]]);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let pair = [2_000_000_000, -1_999_997_980];
+        assert!(matches!(
+            product_of(&pair, 2, 2020),
+            Err(AocError::Overflow { day: 1, .. })
+        ));
+        let pair = [2_000_000_000i64, -1_999_997_980];
+        assert_eq!(product_of(&pair, 2, 2020), Ok(Some(-3999995960000000000)));
+        assert!(k_sum(&[i32::MAX, i32::MAX], 2, 2020).is_empty());
+
+        // Sums past the extremes only steer the search
+        assert_eq!(k_sum(&[i32::MAX, 1, 2019], 2, 2020), vec![vec![1, 2]]);
+        assert_eq!(k_sum(&[i32::MAX, 1, 2019, 0], 3, 2020), vec![vec![1, 2, 3]]);
+        assert_eq!(
+            k_sum(&[i32::MIN, -1, i32::MIN, -5], 2, -6),
+            vec![vec![1, 3]]
+        );
+        assert_eq!(
+            k_sum(&[i32::MIN, 7, i32::MAX, 1], 3, 0),
+            vec![vec![0, 2, 3]]
+        );
+        assert_eq!(product_of(&[i32::MAX, 1, 2019], 2, 2020), Ok(Some(2019)));
+
+        let input = "100000000000000000000\n-99999999999999997980";
+        let pair: Vec<i128> = read_entries(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(product_of(&pair, 2, 2020).is_err());
+        let pair: Vec<BigInt> = read_entries(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            product_of(&pair, 2, BigInt::from(2020)),
+            Ok(Some(
+                "-9999999999999999798000000000000000000000".parse().unwrap()
+            ))
+        );
+    }
+
+    /// The original part 1, loops and all, with `None` where it reached
+    /// `unreachable!()`.
+    fn original_part1(_input: &[i64]) -> Option<i64> {
+        for x in _input.iter() {
+            for y in _input.iter() {
+                if x + y == 2020 {
+                    return Some(x * y);
+                }
+            }
+        }
+        None
+    }
+
+    /// The original part 2, like `original_part1`.
+    fn original_part2(_input: &[i64]) -> Option<i64> {
+        for x in _input.iter() {
+            for y in _input.iter() {
+                for z in _input.iter() {
+                    if x + y + z == 2020 {
+                        return Some(x * y * z);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether the original loops could add up to 2020 using an entry more
+    /// than once, as `x` and `y` go over the same entries. Three times the
+    /// same entry never is 2020.
+    fn reuses_entry(input: &[i64], k: usize) -> bool {
+        match k {
+            2 => input.contains(&1010),
+            _ => input.iter().any(|x| input.contains(&(2020 - 2 * x))),
+        }
+    }
+
+    #[test]
+    fn test_entries_used_once() {
+        assert_eq!(original_part1(&[1010, 3]), Some(1010 * 1010));
+        assert!(part1(&[1010, 3]).is_err());
+        assert_eq!(part1(&[1010, 3, 1010]), Ok(1010 * 1010));
+        assert_eq!(original_part2(&[500, 1020, 3]), Some(500 * 500 * 1020));
+        assert!(part2(&[500, 1020, 3]).is_err());
+        assert_eq!(part2(&[500, 1020, 500]), Ok(500 * 500 * 1020));
+    }
+
+    fn expenses() -> impl Strategy<Value = Vec<i64>> {
+        // Sometimes plant a pair and a triple, random values alone rarely
+        // have an answer
+        (
+            proptest::collection::vec(0..2020i64, 0..30),
+            proptest::option::of(1..2019i64),
+            proptest::option::of((1..1000i64, 1..1000i64)),
+        )
+            .prop_map(|(mut values, pair, triple)| {
+                if let Some(a) = pair {
+                    values.extend(&[a, 2020 - a]);
+                }
+                if let Some((b, c)) = triple {
+                    values.extend(&[b, c, 2020 - b - c]);
+                }
+                values
+            })
+            .prop_shuffle()
+    }
+
+    proptest! {
+        #[test]
+        fn test_k_sum_matches_reference(
+            values in proptest::collection::vec(-10..10, 0..12),
+            k in 0usize..5,
+            target in -20..20,
+        ) {
+            let expected: Vec<Vec<usize>> = (0..values.len())
+                .combinations(k)
+                .filter(|c| c.iter().map(|i| values[*i]).sum::<i32>() == target)
+                .collect();
+            prop_assert_eq!(k_sum(&values, k, target), expected);
+        }
+
+        #[test]
+        fn test_k_sum_extremes(
+            values in proptest::collection::vec(
+                prop_oneof![Just(i32::MIN), Just(i32::MAX), Just(i32::MIN + 1), -3..3],
+                0..10,
+            ),
+            k in 0usize..5,
+            target in prop_oneof![Just(i32::MIN), Just(i32::MAX), -3..3],
+        ) {
+            let expected: Vec<Vec<usize>> = (0..values.len())
+                .combinations(k)
+                .filter(|c| c.iter().map(|i| values[*i] as i64).sum::<i64>() == target as i64)
+                .collect();
+            prop_assert_eq!(k_sum(&values, k, target), expected);
+        }
+
+        // Both find the first combination in index order, so they agree
+        // on the product whenever the original doesn't reuse an entry
+        #[test]
+        fn test_p1_matches_reference(values in expenses()) {
+            prop_assume!(!reuses_entry(&values, 2));
+            prop_assert_eq!(part1(&values).ok(), original_part1(&values));
+        }
+
+        #[test]
+        fn test_p2_matches_reference(values in expenses()) {
+            prop_assume!(!reuses_entry(&values, 3));
+            prop_assert_eq!(part2(&values).ok(), original_part2(&values));
+        }
     }
 }
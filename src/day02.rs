@@ -1,26 +1,107 @@
+use crate::error::{column, parse_token};
+use crate::stream::records;
+use crate::{AocError, Solver, StreamSolver};
 use aoc_runner_derive::{aoc, aoc_generator};
 use lazy_static::lazy_static;
 use regex::Regex;
-use simple_error::bail;
-use std::error::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::BufRead;
+use std::str::FromStr;
+use unicode_segmentation::UnicodeSegmentation;
 
-struct PasswordRow {
-    lb: usize,
-    ub: usize,
-    character: String,
-    password: String,
+const DAY: u32 = 2;
+
+/// A password together with the policy it was created under. Characters
+/// are grapheme clusters, so `character` is a single one and positions count
+/// them rather than bytes or code points.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PasswordRow {
+    pub lb: usize,
+    pub ub: usize,
+    pub character: String,
+    pub password: String,
+}
+
+impl PasswordRow {
+    /// Character at the 1-based `position` of the password, or why there's
+    /// none.
+    pub fn character_at(&self, position: usize) -> Result<&str, String> {
+        let len = self.password.graphemes(true).count();
+        position
+            .checked_sub(1)
+            .and_then(|idx| self.password.graphemes(true).nth(idx))
+            .ok_or_else(|| {
+                format!(
+                    "position {} is past the end of the {} character password",
+                    position, len
+                )
+            })
+    }
+
+    /// Checks what the puzzle format can't express, so the row can be
+    /// written out and parsed back unchanged.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.lb == 0 {
+            return Err("positions start at 1".to_string());
+        }
+        if self.character.graphemes(true).count() != 1 || self.character.trim().is_empty() {
+            return Err(format!(
+                "expected a single character, found {:?}",
+                self.character
+            ));
+        }
+        if self.password.is_empty() || self.password.chars().any(char::is_whitespace) {
+            return Err(format!(
+                "expected a password without spaces, found {:?}",
+                self.password
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Times the policy character appears in the password.
+    pub fn count(&self) -> usize {
+        self.password
+            .graphemes(true)
+            .filter(|g| *g == self.character)
+            .count()
+    }
 }
 
+/// Parses `lb-ub c: password` rows.
 #[aoc_generator(day2)]
-fn parse_input_day2(input: &str) -> Result<Vec<PasswordRow>, Box<dyn Error>> {
-    input.lines().map(|l| parse_line(l)).collect()
+pub fn parse_input_day2(input: &str) -> Result<Vec<PasswordRow>, AocError> {
+    read_rows(input.as_bytes()).collect()
+}
+
+/// Parses the rows one line at a time out of `reader`.
+pub fn read_rows<R: BufRead>(reader: R) -> impl Iterator<Item = Result<PasswordRow, AocError>> {
+    records(reader, |idx, l| {
+        parse_line(l).map_err(|e| e.offset_lines(idx))
+    })
 }
 
-#[allow(dead_code)]
-fn parse_line(line: &str) -> Result<PasswordRow, Box<dyn Error>> {
+/// Counts the rows of `reader` complying with `policy`, one row at a time.
+fn count_valid(reader: &mut dyn BufRead, policy: &dyn PasswordPolicy) -> Result<usize, AocError> {
+    let mut count = 0;
+    for row in read_rows(reader) {
+        if policy.check(&row?).is_ok() {
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+/// Parses a single row, reporting errors as if it was the first line.
+fn parse_line(line: &str) -> Result<PasswordRow, AocError> {
     lazy_static! {
         static ref MATCHER: Regex =
-            Regex::new(r"(?P<lb>\d+)-(?P<ub>\d+)\s(?P<character>\w+):\s(?P<password>\w+)").unwrap();
+            Regex::new(r"^(?P<lb>\d+)-(?P<ub>\d+)\s(?P<character>\S+):\s(?P<password>\S+)$")
+                .unwrap();
     }
     match MATCHER.captures(line) {
         Some(caps) => {
@@ -29,48 +110,415 @@ fn parse_line(line: &str) -> Result<PasswordRow, Box<dyn Error>> {
             let character = caps.name("character").unwrap();
             let password = caps.name("password").unwrap();
             let row = PasswordRow {
-                lb: lb.parse()?,
-                ub: ub.parse()?,
+                lb: parse_token(DAY, 0, line, lb)?,
+                ub: parse_token(DAY, 0, line, ub)?,
                 character: character.to_string(),
                 password: password.to_string(),
             };
 
+            if row.lb == 0 {
+                return Err(AocError::parse(
+                    DAY,
+                    1,
+                    column(line, lb),
+                    lb,
+                    "positions start at 1",
+                ));
+            }
+            if row.character.graphemes(true).count() != 1 {
+                return Err(AocError::parse(
+                    DAY,
+                    1,
+                    column(line, character),
+                    character,
+                    "expected a single character",
+                ));
+            }
+
             Ok(row)
         }
-        None => bail!("No matches!"),
+        None => Err(AocError::parse(
+            DAY,
+            1,
+            1,
+            line,
+            "expected a row like `1-3 a: abcde`",
+        )),
+    }
+}
+
+/// Writes the row in the puzzle format, `1-3 a: abcde`.
+impl fmt::Display for PasswordRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{} {}: {}",
+            self.lb, self.ub, self.character, self.password
+        )
+    }
+}
+
+/// Formats password rows can be imported from and exported to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// One `1-3 a: abcde` row per line, like the puzzle input.
+    Puzzle,
+    /// A `lb,ub,character,password` header followed by a record per row.
+    Csv,
+    /// An array of objects with the same fields as the CSV header.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = AocError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "puzzle" => Ok(Format::Puzzle),
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(AocError::invalid_argument(format!(
+                "unknown format `{}`, expected puzzle, csv or json",
+                s
+            ))),
+        }
+    }
+}
+
+/// Writes `rows` in `format`.
+pub fn export(rows: &[PasswordRow], format: Format) -> Result<String, AocError> {
+    let exported = match format {
+        Format::Puzzle => rows.iter().map(|r| format!("{}\n", r)).collect(),
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            for row in rows {
+                writer.serialize(row).map_err(export_error)?;
+            }
+            let bytes = writer.into_inner().map_err(export_error)?;
+            String::from_utf8(bytes).map_err(export_error)?
+        }
+        Format::Json => serde_json::to_string_pretty(rows).map_err(export_error)?,
+    };
+
+    Ok(exported)
+}
+
+fn export_error(e: impl fmt::Display) -> AocError {
+    AocError::invalid_argument(format!("exporting passwords: {}", e))
+}
+
+/// Reads rows written in `format`, rejecting the ones the puzzle format
+/// couldn't hold.
+pub fn import(text: &str, format: Format) -> Result<Vec<PasswordRow>, AocError> {
+    let rows: Vec<PasswordRow> = match format {
+        Format::Puzzle => return parse_input_day2(text),
+        Format::Csv => csv::Reader::from_reader(text.as_bytes())
+            .deserialize()
+            .collect::<Result<_, csv::Error>>()
+            .map_err(|e| AocError::invalid_argument(format!("passwords csv: {}", e)))?,
+        Format::Json => serde_json::from_str(text)
+            .map_err(|e| AocError::invalid_argument(format!("passwords json: {}", e)))?,
+    };
+    for (idx, row) in rows.iter().enumerate() {
+        row.validate().map_err(|reason| {
+            AocError::invalid_argument(format!("password row {}: {}", idx + 1, reason))
+        })?;
     }
+
+    Ok(rows)
 }
 
+/// Counts the passwords whose policy character count is within bounds.
 #[aoc(day2, part1)]
-fn part1(input: &[PasswordRow]) -> usize {
-    input.iter().filter(|x| password_valid(x)).count()
+pub fn part1(input: &[PasswordRow]) -> usize {
+    input.iter().filter(|x| CountRange.check(x).is_ok()).count()
 }
 
+/// Counts the passwords with the policy character in exactly one of the two positions.
 #[aoc(day2, part2)]
-fn part2(input: &[PasswordRow]) -> usize {
-    input.iter().filter(|x| password_valid2(x)).count()
+pub fn part2(input: &[PasswordRow]) -> usize {
+    input
+        .iter()
+        .filter(|x| PositionalXor.check(x).is_ok())
+        .count()
+}
+
+/// A rule password rows can be checked against.
+pub trait PasswordPolicy {
+    /// Name the policy is looked up by in `policy`.
+    fn name(&self) -> &str;
+
+    /// Whether `row` complies with the policy, or why it doesn't.
+    fn check(&self, row: &PasswordRow) -> Result<(), String>;
+}
+
+/// The policy character appears between `lb` and `ub` times.
+pub struct CountRange;
+
+/// The policy character is at exactly one of the 1-based positions `lb` and `ub`.
+pub struct PositionalXor;
+
+/// The policy character is at both of the 1-based positions `lb` and `ub`.
+pub struct PositionalAnd;
+
+/// The password matches a regular expression, regardless of the row policy.
+pub struct Pattern {
+    /// Full name, like `regex:^[a-z]+$`.
+    name: String,
+    regex: Regex,
+}
+
+/// The password carries at least the given number of bits of Shannon
+/// entropy, computed from the frequency of its characters.
+pub struct MinEntropy {
+    /// Full name, like `min-entropy:8`.
+    name: String,
+    bits: f64,
+}
+
+/// Built-in policies accepted by `policy`. The ones taking a parameter are
+/// written `name:parameter`.
+pub const POLICIES: &[&str] = &[
+    "count-range",
+    "positional-xor",
+    "positional-and",
+    "regex:<pattern>",
+    "min-entropy:<bits>",
+];
+
+/// Looks up a built-in policy by name, like `count-range` or `regex:^[a-z]+$`.
+pub fn policy(name: &str) -> Result<Box<dyn PasswordPolicy>, AocError> {
+    let full_name = name;
+    let (name, parameter) = match name.split_once(':') {
+        Some((name, parameter)) => (name, Some(parameter)),
+        None => (name, None),
+    };
+    let policy: Box<dyn PasswordPolicy> = match (name, parameter) {
+        ("count-range", None) => Box::new(CountRange),
+        ("positional-xor", None) => Box::new(PositionalXor),
+        ("positional-and", None) => Box::new(PositionalAnd),
+        ("regex", Some(pattern)) => Box::new(Pattern {
+            name: full_name.to_string(),
+            regex: Regex::new(pattern)
+                .map_err(|e| AocError::invalid_argument(format!("policy regex: {}", e)))?,
+        }),
+        ("min-entropy", Some(bits)) => {
+            let threshold: f64 = bits.parse().map_err(|e| {
+                AocError::invalid_argument(format!("policy min-entropy: {}: {:?}", e, bits))
+            })?;
+            if !threshold.is_finite() || threshold < 0.0 {
+                return Err(AocError::invalid_argument(format!(
+                    "policy min-entropy: expected a finite, non-negative number of bits, got {:?}",
+                    bits
+                )));
+            }
+            Box::new(MinEntropy {
+                name: full_name.to_string(),
+                bits: threshold,
+            })
+        }
+        _ => {
+            return Err(AocError::invalid_argument(format!(
+                "unknown password policy `{}`, expected one of {}",
+                name,
+                POLICIES.join(", ")
+            )))
+        }
+    };
+
+    Ok(policy)
+}
+
+/// Whether the policy character is at the 1-based `position` of the
+/// password, failing for positions past its end.
+fn has_character_at(row: &PasswordRow, position: usize) -> Result<bool, String> {
+    Ok(row.character_at(position)? == row.character)
+}
+
+impl PasswordPolicy for CountRange {
+    fn name(&self) -> &str {
+        "count-range"
+    }
+
+    fn check(&self, row: &PasswordRow) -> Result<(), String> {
+        let count = row.count();
+        if count >= row.lb && count <= row.ub {
+            Ok(())
+        } else {
+            Err(format!(
+                "`{}` appears {} times, expected {} to {}",
+                row.character, count, row.lb, row.ub
+            ))
+        }
+    }
+}
+
+impl PasswordPolicy for PositionalXor {
+    fn name(&self) -> &str {
+        "positional-xor"
+    }
+
+    fn check(&self, row: &PasswordRow) -> Result<(), String> {
+        match (
+            has_character_at(row, row.lb)?,
+            has_character_at(row, row.ub)?,
+        ) {
+            (true, false) | (false, true) => Ok(()),
+            (true, true) => Err(format!(
+                "`{}` is at both positions {} and {}",
+                row.character, row.lb, row.ub
+            )),
+            (false, false) => Err(format!(
+                "`{}` is at neither position {} nor {}",
+                row.character, row.lb, row.ub
+            )),
+        }
+    }
 }
 
-fn password_valid(psswd: &PasswordRow) -> bool {
-    let char_count = psswd.password.matches(&psswd.character).count();
-    char_count >= psswd.lb && char_count <= psswd.ub
+impl PasswordPolicy for PositionalAnd {
+    fn name(&self) -> &str {
+        "positional-and"
+    }
+
+    fn check(&self, row: &PasswordRow) -> Result<(), String> {
+        let mut missing = vec![];
+        for position in [row.lb, row.ub] {
+            if !has_character_at(row, position)? {
+                missing.push(position.to_string());
+            }
+        }
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "`{}` is not at position {}",
+                row.character,
+                missing.join(" nor ")
+            ))
+        }
+    }
+}
+
+impl PasswordPolicy for Pattern {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self, row: &PasswordRow) -> Result<(), String> {
+        if self.regex.is_match(&row.password) {
+            Ok(())
+        } else {
+            Err(format!("does not match `{}`", self.regex))
+        }
+    }
 }
 
-fn password_valid2(psswd: &PasswordRow) -> bool {
-    let psswd_chars: Vec<String> = psswd.password.chars().map(|c| c.to_string()).collect();
-    xor(
-        psswd_chars[psswd.lb - 1] == psswd.character,
-        psswd_chars[psswd.ub - 1] == psswd.character,
-    )
+impl MinEntropy {
+    /// Bits of Shannon entropy of `password`, over its grapheme clusters.
+    pub fn entropy(password: &str) -> f64 {
+        let mut counts = HashMap::new();
+        for g in password.graphemes(true) {
+            *counts.entry(g).or_insert(0) += 1;
+        }
+        let len = password.graphemes(true).count() as f64;
+        let per_char: f64 = counts
+            .values()
+            .map(|n| {
+                let p = *n as f64 / len;
+                -p * p.log2()
+            })
+            .sum();
+
+        per_char * len
+    }
+}
+
+impl PasswordPolicy for MinEntropy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self, row: &PasswordRow) -> Result<(), String> {
+        let bits = MinEntropy::entropy(&row.password);
+        if bits >= self.bits {
+            Ok(())
+        } else {
+            Err(format!(
+                "has {:.2} bits of entropy, expected at least {}",
+                bits, self.bits
+            ))
+        }
+    }
+}
+
+/// A row that doesn't comply with one of the policies it was checked against.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Failure {
+    /// 1-based line of the row.
+    pub line: usize,
+    pub policy: String,
+    pub reason: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}: {}", self.line, self.policy, self.reason)
+    }
+}
+
+/// Every failure of every row against every policy, in row order.
+pub fn report(rows: &[PasswordRow], policies: &[Box<dyn PasswordPolicy>]) -> Vec<Failure> {
+    let mut failures = vec![];
+    for (idx, row) in rows.iter().enumerate() {
+        for policy in policies {
+            if let Err(reason) = policy.check(row) {
+                failures.push(Failure {
+                    line: idx + 1,
+                    policy: policy.name().to_string(),
+                    reason,
+                });
+            }
+        }
+    }
+
+    failures
+}
+
+/// Solver for day 2, usable outside of cargo-aoc.
+pub struct Day02;
+
+impl Solver for Day02 {
+    type Input = Vec<PasswordRow>;
+    type Output = usize;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        parse_input_day2(input)
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(part1(input))
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(part2(input))
+    }
 }
 
-fn xor(a: bool, b: bool) -> bool {
-    (a && !b) | (!a && b)
+impl StreamSolver for Day02 {
+    fn part1_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        count_valid(reader, &CountRange)
+    }
+
+    fn part2_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        count_valid(reader, &PositionalXor)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
     const EXAMPLE_INPUT: &str = "1-3 a: abcde\n1-3 b: cdefg\n2-9 c: ccccccccc";
     #[test]
     fn test_parse_input() {
@@ -102,16 +550,179 @@ mod tests {
         let line2 = "1-3 b: cdefg";
         let passwd = parse_line(line).unwrap();
         let passwd2 = parse_line(line2).unwrap();
-        assert_eq!(password_valid(&passwd), true);
-        assert_eq!(password_valid(&passwd2), false);
+        assert_eq!(CountRange.check(&passwd), Ok(()));
+        assert_eq!(
+            CountRange.check(&passwd2),
+            Err("`b` appears 0 times, expected 1 to 3".to_string())
+        );
     }
     #[test]
     fn check_valid2() {
         let valid = parse_line("1-3 a: abcde").unwrap();
         let invalid = parse_line("1-3 b: cdefg").unwrap();
         let invalid2 = parse_line("2-9 c: ccccccccc").unwrap();
-        assert_eq!(password_valid2(&valid), true);
-        assert_eq!(password_valid2(&invalid), false);
-        assert_eq!(password_valid2(&invalid2), false);
+        assert_eq!(PositionalXor.check(&valid), Ok(()));
+        assert!(PositionalXor.check(&invalid).is_err());
+        assert_eq!(
+            PositionalXor.check(&invalid2),
+            Err("`c` is at both positions 2 and 9".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unicode() {
+        // `é` written as `e` and a combining accent is still one character
+        let row = parse_line("2-3 e\u{301}: ae\u{301}🦀e\u{301}").unwrap();
+        assert_eq!(row.character_at(2), Ok("e\u{301}"));
+        assert_eq!(row.character_at(3), Ok("🦀"));
+        assert_eq!(row.count(), 2);
+        assert_eq!(CountRange.check(&row), Ok(()));
+        assert!(PositionalXor.check(&row).is_ok());
+        assert!(parse_line("1-3 ñ: piñata").is_ok());
+
+        let row = parse_line("2-12 a: abc").unwrap();
+        assert_eq!(
+            PositionalXor.check(&row),
+            Err("position 12 is past the end of the 3 character password".to_string())
+        );
+        assert!(PositionalAnd.check(&row).is_err());
+        assert_eq!(
+            parse_line("1-3 ab: abab").err(),
+            Some(AocError::parse(
+                2,
+                1,
+                5,
+                "ab",
+                "expected a single character"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_export() {
+        let rows = parse_input_day2(EXAMPLE_INPUT).unwrap();
+        assert_eq!(rows[0].to_string(), "1-3 a: abcde");
+        assert_eq!(
+            export(&rows[..1], Format::Csv).unwrap(),
+            "lb,ub,character,password\n1,3,a,abcde\n"
+        );
+        let json = export(&rows[..1], Format::Json).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::json!([{"lb": 1, "ub": 3, "character": "a", "password": "abcde"}])
+        );
+
+        assert!(import("lb,ub,character,password\n0,3,a,abcde\n", Format::Csv).is_err());
+        assert!(import("lb,ub,character,password\n1,3,a,abc de\n", Format::Csv).is_err());
+        assert!(import(
+            r#"[{"lb": 1, "ub": 3, "character": "ab", "password": "a"}]"#,
+            Format::Json
+        )
+        .is_err());
+        assert!(import("[{}]", Format::Json).is_err());
+        assert!("xml".parse::<Format>().is_err());
+    }
+
+    fn rows() -> impl Strategy<Value = Vec<PasswordRow>> {
+        let characters = vec!["a", "z", "ñ", "e\u{301}", "🦀", ":", ",", "\"", "-"];
+        let row = (
+            1usize..30,
+            0usize..10,
+            proptest::sample::select(characters.clone()),
+            proptest::collection::vec(proptest::sample::select(characters), 1..20),
+        )
+            .prop_map(|(lb, extra, character, password)| PasswordRow {
+                lb,
+                ub: lb + extra,
+                character: character.to_string(),
+                password: password.concat(),
+            });
+        proptest::collection::vec(row, 0..10)
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(rows in rows()) {
+            for format in [Format::Puzzle, Format::Csv, Format::Json] {
+                let exported = export(&rows, format).unwrap();
+                prop_assert_eq!(&import(&exported, format).unwrap(), &rows);
+            }
+        }
+    }
+
+    #[test]
+    fn test_policies() {
+        let rows = parse_input_day2(EXAMPLE_INPUT).unwrap();
+        let and = policy("positional-and").unwrap();
+        assert!(and.check(&rows[0]).is_err());
+        assert_eq!(and.check(&rows[2]), Ok(()));
+
+        let lowercase = policy("regex:^[a-e]+$").unwrap();
+        assert_eq!(lowercase.check(&rows[0]), Ok(()));
+        assert_eq!(
+            lowercase.check(&rows[1]),
+            Err("does not match `^[a-e]+$`".to_string())
+        );
+
+        assert_eq!(MinEntropy::entropy("ccccccccc"), 0.0);
+        assert!((MinEntropy::entropy("abcd") - 8.0).abs() < 1e-9);
+        let entropy = policy("min-entropy:8").unwrap();
+        assert!(entropy.check(&rows[0]).is_ok());
+        assert!(entropy.check(&rows[2]).is_err());
+
+        assert!(policy("count").is_err());
+        assert!(policy("regex:(").is_err());
+        assert!(policy("min-entropy:lots").is_err());
+        for bits in ["nan", "inf", "-inf", "-1"] {
+            assert!(policy(&format!("min-entropy:{}", bits)).is_err());
+        }
+        assert!(policy("min-entropy:0").is_ok());
+    }
+
+    #[test]
+    fn test_report() {
+        let rows = parse_input_day2(EXAMPLE_INPUT).unwrap();
+        let policies = vec![
+            policy("count-range").unwrap(),
+            policy("positional-xor").unwrap(),
+        ];
+        let failures = report(&rows, &policies);
+        let failed: Vec<(usize, &str)> = failures
+            .iter()
+            .map(|f| (f.line, f.policy.as_str()))
+            .collect();
+        assert_eq!(
+            failed,
+            vec![
+                (2, "count-range"),
+                (2, "positional-xor"),
+                (3, "positional-xor")
+            ]
+        );
+        assert_eq!(
+            failures[2].to_string(),
+            "line 3: positional-xor: `c` is at both positions 2 and 9"
+        );
+
+        let policies = vec![
+            policy("regex:^a").unwrap(),
+            policy("min-entropy:8").unwrap(),
+        ];
+        let failed: Vec<String> = report(&rows, &policies)
+            .iter()
+            .map(|f| f.policy.clone())
+            .collect();
+        assert_eq!(failed, vec!["regex:^a", "regex:^a", "min-entropy:8"]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = parse_input_day2("1-3 a: abcde\n1-3 b cdefg").err().unwrap();
+        assert_eq!(
+            err,
+            AocError::parse(2, 2, 1, "1-3 b cdefg", "expected a row like `1-3 a: abcde`")
+        );
+        let err = parse_line("0-3 a: abcde").err().unwrap();
+        assert_eq!(err, AocError::parse(2, 1, 1, "0", "positions start at 1"));
     }
 }
@@ -1,96 +1,1102 @@
+use crate::{AocError, Solver};
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::fmt;
+use std::str::FromStr;
+
+const DAY: u32 = 3;
 
 type Grid = Vec<Vec<char>>;
 
-struct Map {
+/// Toboggan map, repeating itself infinitely to the right.
+pub struct Map {
     grid: Grid,
     n: usize,
     m: usize,
+    legend: Legend,
+}
+
+/// Kind of terrain a character of the map stands for, and what hitting it
+/// costs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub name: String,
+    pub cost: u64,
+}
+
+/// Tiles a map may contain, by the character they're drawn with, in the
+/// order they were added.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Legend {
+    tiles: Vec<(char, Tile)>,
+}
+
+impl Legend {
+    /// A legend without any tile.
+    pub fn empty() -> Self {
+        Legend { tiles: vec![] }
+    }
+
+    /// Adds or replaces the tile drawn as `symbol`.
+    pub fn with(mut self, symbol: char, name: &str, cost: u64) -> Self {
+        let tile = Tile {
+            name: name.to_string(),
+            cost,
+        };
+        match self.tiles.iter_mut().find(|(s, _)| *s == symbol) {
+            Some(entry) => entry.1 = tile,
+            None => self.tiles.push((symbol, tile)),
+        }
+        self
+    }
+
+    pub fn get(&self, symbol: char) -> Option<&Tile> {
+        self.tiles
+            .iter()
+            .find(|(s, _)| *s == symbol)
+            .map(|(_, tile)| tile)
+    }
+
+    /// What the characters of a legend are, for error messages.
+    fn expected(&self) -> String {
+        let symbols: Vec<String> = self.tiles.iter().map(|(c, _)| format!("`{}`", c)).collect();
+        match symbols.len() {
+            2 => format!("expected {} or {}", symbols[0], symbols[1]),
+            _ => format!("expected one of {}", symbols.join(", ")),
+        }
+    }
+}
+
+/// The puzzle legend: `.` is open ground and `#` a tree, costing 1.
+impl Default for Legend {
+    fn default() -> Self {
+        Legend::empty().with('.', "open", 0).with('#', "tree", 1)
+    }
+}
+
+/// Written as `.=open:0,#=tree:1`.
+impl fmt::Display for Legend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tiles: Vec<String> = self
+            .tiles
+            .iter()
+            .map(|(symbol, tile)| format!("{}={}:{}", symbol, tile.name, tile.cost))
+            .collect();
+        write!(f, "{}", tiles.join(","))
+    }
+}
+
+impl FromStr for Legend {
+    type Err = AocError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut legend = Legend::empty();
+        for entry in s.split(',') {
+            let invalid = || {
+                AocError::invalid_argument(format!(
+                    "expected a legend entry like `#=tree:1`, found `{}`",
+                    entry
+                ))
+            };
+            let mut chars = entry.chars();
+            let symbol = chars.next().ok_or_else(invalid)?;
+            let (name, cost) = chars
+                .as_str()
+                .strip_prefix('=')
+                .and_then(|tile| tile.rsplit_once(':'))
+                .ok_or_else(invalid)?;
+            if name.is_empty() || legend.get(symbol).is_some() {
+                return Err(invalid());
+            }
+            legend = legend.with(symbol, name, cost.parse().map_err(|_| invalid())?);
+        }
+
+        Ok(legend)
+    }
+}
+
+/// Tiles of each kind hit along a trace, and what they cost.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Breakdown {
+    /// Hits and total cost by tile name.
+    pub tiles: BTreeMap<String, (usize, u64)>,
+}
+
+impl Breakdown {
+    pub fn hits(&self, name: &str) -> usize {
+        self.tiles.get(name).map_or(0, |(hits, _)| *hits)
+    }
+
+    /// Cost of every tile hit, or an error when it doesn't fit a `u64`.
+    pub fn total(&self) -> Result<u64, AocError> {
+        self.tiles
+            .values()
+            .try_fold(0u64, |total, (_, cost)| total.checked_add(*cost))
+            .ok_or_else(|| AocError::overflow(DAY, "the total cost of a trace"))
+    }
+}
+
+/// Written as `open: 4 (0), tree: 7 (7), total 7`.
+impl fmt::Display for Breakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, (hits, cost)) in &self.tiles {
+            write!(f, "{}: {} ({}), ", name, hits, cost)?;
+        }
+        match self.total() {
+            Ok(total) => write!(f, "total {}", total),
+            Err(_) => write!(f, "total over {}", u64::MAX),
+        }
+    }
 }
 
 impl Map {
     pub fn get_char_at(&self, i: usize, j: usize) -> char {
         return self.grid[i % self.m][j % self.n];
     }
+
+    /// Rows of the map, the last one being the bottom.
+    pub fn height(&self) -> usize {
+        self.m
+    }
+
+    /// Columns of the map before it repeats itself.
+    pub fn width(&self) -> usize {
+        self.n
+    }
+
+    pub fn legend(&self) -> &Legend {
+        &self.legend
+    }
+
+    /// Tile drawn at `(row, column)`, wrapping around like `get_char_at`.
+    pub fn tile_at(&self, i: usize, j: usize) -> &Tile {
+        let symbol = self.get_char_at(i, j);
+        self.legend
+            .get(symbol)
+            .expect("maps only hold characters of their legend")
+    }
+
+    /// Tiles hit going down `slope`, by kind. Fails when the total cost
+    /// doesn't fit a `u64`.
+    pub fn breakdown(&self, slope: Slope) -> Result<Breakdown, AocError> {
+        let mut breakdown = Breakdown::default();
+        let mut total = 0u64;
+        for (_, symbol) in self.trace(slope) {
+            let tile = self
+                .legend
+                .get(symbol)
+                .expect("maps only hold characters of their legend");
+            // Costs of a kind never exceed the total, so they fit when it does
+            total = total.checked_add(tile.cost).ok_or_else(|| {
+                AocError::overflow(DAY, format!("the cost of going down {}", slope))
+            })?;
+            let entry = breakdown.tiles.entry(tile.name.clone()).or_insert((0, 0));
+            entry.0 += 1;
+            entry.1 += tile.cost;
+        }
+
+        Ok(breakdown)
+    }
+
+    /// Every tile visited going down `slope` from the top left corner until
+    /// the bottom, along with its `(row, column)`. Columns keep growing
+    /// instead of wrapping around.
+    pub fn trace(&self, slope: Slope) -> Trace<'_> {
+        Trace {
+            map: self,
+            slope,
+            step: 0,
+        }
+    }
 }
 
+/// Movement of the toboggan on every step: `right` columns, which may be a
+/// fraction, and `down` rows. Fractional columns are rounded down, so a
+/// right of 1/2 moves one column every other step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Slope {
+    right: usize,
+    /// Denominator of `right`, in lowest terms with it.
+    per: usize,
+    down: usize,
+}
+
+impl Slope {
+    /// The puzzle slopes, moving whole columns.
+    pub fn new(right: usize, down: usize) -> Result<Slope, AocError> {
+        Slope::fraction(right, 1, down)
+    }
+
+    /// Moving `right / per` columns on every step.
+    pub fn fraction(right: usize, per: usize, down: usize) -> Result<Slope, AocError> {
+        if per == 0 || down == 0 {
+            return Err(AocError::invalid_argument(format!(
+                "slope {}/{} right, {} down has to move down and cannot divide by 0",
+                right, per, down
+            )));
+        }
+        let divisor = gcd(right, per);
+        Ok(Slope {
+            right: right / divisor,
+            per: per / divisor,
+            down,
+        })
+    }
+
+    /// Every distinct slope moving at most `max_right` columns and
+    /// `max_down` rows, with a denominator of at most `max_per`, in order.
+    pub fn all(max_right: usize, max_per: usize, max_down: usize) -> Vec<Slope> {
+        let mut slopes = vec![];
+        for down in 1..=max_down {
+            for per in 1..=max_per {
+                for right in 0..=max_right * per {
+                    if gcd(right, per) == 1 {
+                        slopes.push(Slope { right, per, down });
+                    }
+                }
+            }
+        }
+        slopes.sort();
+        slopes.dedup();
+        slopes
+    }
+
+    /// Column reached after `step` steps.
+    fn column(&self, step: usize) -> usize {
+        step * self.right / self.per
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a.max(1)
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Written as `3,1`, or `1/2,1` for fractions of a column.
+impl fmt::Display for Slope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.per == 1 {
+            write!(f, "{},{}", self.right, self.down)
+        } else {
+            write!(f, "{}/{},{}", self.right, self.per, self.down)
+        }
+    }
+}
+
+impl FromStr for Slope {
+    type Err = AocError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            AocError::invalid_argument(format!(
+                "expected a slope like `3,1` or `1/2,1`, found `{}`",
+                s
+            ))
+        };
+        let (right, down) = s.split_once(',').ok_or_else(invalid)?;
+        let (right, per) = right.split_once('/').unwrap_or((right, "1"));
+        let number = |n: &str| n.trim().parse::<usize>().map_err(|_| invalid());
+
+        Slope::fraction(number(right)?, number(per)?, number(down)?)
+    }
+}
+
+/// Iterator returned by `Map::trace`.
+pub struct Trace<'a> {
+    map: &'a Map,
+    slope: Slope,
+    step: usize,
+}
+
+impl Iterator for Trace<'_> {
+    type Item = ((usize, usize), char);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.step * self.slope.down;
+        if row >= self.map.m {
+            return None;
+        }
+        let column = self.slope.column(self.step);
+        self.step += 1;
+
+        Some(((row, column), self.map.get_char_at(row, column)))
+    }
+}
+
+/// Whether `best_slope` looks for the lowest or the highest cost.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Goal {
+    Fewest,
+    Most,
+}
+
+/// Slope among `slopes` with the lowest or highest total cost, which with
+/// the puzzle legend is the number of trees hit, along with that cost. Ties
+/// go to the first one.
+pub fn best_slope(
+    map: &Map,
+    slopes: impl IntoIterator<Item = Slope>,
+    goal: Goal,
+) -> Result<Option<(Slope, u64)>, AocError> {
+    let mut best: Option<(Slope, u64)> = None;
+    for slope in slopes {
+        let cost = map.breakdown(slope)?.total()?;
+        let better = match (best, goal) {
+            (None, _) => true,
+            (Some((_, b)), Goal::Fewest) => cost < b,
+            (Some((_, b)), Goal::Most) => cost > b,
+        };
+        if better {
+            best = Some((slope, cost));
+        }
+    }
+
+    Ok(best)
+}
+
+/// Steps allowed between tiles when looking for a route, as `(down, right)`
+/// offsets. Negative ones go up or left, and columns wrap around.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Moves(Vec<(isize, isize)>);
+
+impl Moves {
+    pub fn new(offsets: Vec<(isize, isize)>) -> Result<Moves, AocError> {
+        if offsets.is_empty() || offsets.contains(&(0, 0)) {
+            return Err(AocError::invalid_argument(
+                "moves have to go somewhere, and there has to be at least one",
+            ));
+        }
+        Ok(Moves(offsets))
+    }
+
+    /// One tile right or one tile down.
+    pub fn right_down() -> Moves {
+        Moves(vec![(0, 1), (1, 0)])
+    }
+
+    /// One tile in any of the eight directions.
+    pub fn king() -> Moves {
+        let mut offsets = vec![];
+        for down in -1..=1 {
+            for right in -1..=1 {
+                if (down, right) != (0, 0) {
+                    offsets.push((down, right));
+                }
+            }
+        }
+        Moves(offsets)
+    }
+}
+
+/// `right-down`, `king`, or `down,right` offsets separated by `;`.
+impl FromStr for Moves {
+    type Err = AocError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "right-down" => Ok(Moves::right_down()),
+            "king" => Ok(Moves::king()),
+            _ => {
+                let offsets = s
+                    .split(';')
+                    .map(|offset| {
+                        let (down, right) = offset.split_once(',')?;
+                        Some((down.trim().parse().ok()?, right.trim().parse().ok()?))
+                    })
+                    .collect::<Option<Vec<(isize, isize)>>>()
+                    .ok_or_else(|| {
+                        AocError::invalid_argument(format!(
+                            "expected `right-down`, `king` or offsets like `1,0;0,1`, found `{}`",
+                            s
+                        ))
+                    })?;
+                Moves::new(offsets)
+            }
+        }
+    }
+}
+
+/// Cheapest way found from the top row to the bottom one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Route {
+    /// Cost of every tile of the route, the first one included.
+    pub cost: u64,
+    /// `(row, column)` of every tile, with columns wrapped into the map.
+    pub tiles: Vec<(usize, usize)>,
+}
+
+/// Cheapest route from any tile of the top row to any of the bottom row,
+/// taking `moves`, with tiles costing what the legend says. With the puzzle
+/// legend that's the route hitting the fewest trees.
+///
+/// This is A*, estimating the rest of the route as the rows left to go down
+/// at the cheapest tile cost, so it's Dijkstra when open tiles are free.
+/// Fails when every route reaching the bottom costs more than a `u64` holds.
+pub fn cheapest_route(map: &Map, moves: &Moves) -> Result<Option<Route>, AocError> {
+    let (m, n) = (map.m, map.n);
+    let max_down = match moves.0.iter().map(|(down, _)| *down).max() {
+        Some(down) => down,
+        None => return Ok(None),
+    };
+    if max_down <= 0 {
+        return Ok(if m == 1 { top_row_route(map) } else { None });
+    }
+    let min_cost = match map.legend.tiles.iter().map(|(_, t)| t.cost).min() {
+        Some(cost) => cost,
+        None => return Ok(None),
+    };
+    // `None` when even the estimate doesn't fit, so no route through it does
+    let priority = |cost: u64, row: usize| {
+        let rows_left = (m - 1 - row) as u64;
+        rows_left
+            .div_ceil(max_down as u64)
+            .checked_mul(min_cost)
+            .and_then(|estimate| estimate.checked_add(cost))
+    };
+
+    let mut costs = vec![None; m * n];
+    let mut previous = vec![None; m * n];
+    let mut queue = BinaryHeap::new();
+    // Routes left out for costing too much, which only matter when no other
+    // one reaches the bottom
+    let mut overflowed = false;
+    for (j, cost) in costs[..n].iter_mut().enumerate() {
+        let tile = map.tile_at(0, j).cost;
+        *cost = Some(tile);
+        match priority(tile, 0) {
+            Some(priority) => queue.push(Reverse((priority, tile, 0, j))),
+            None => overflowed = true,
+        }
+    }
+
+    while let Some(Reverse((_, cost, i, j))) = queue.pop() {
+        if costs[i * n + j].is_some_and(|best| cost > best) {
+            continue;
+        }
+        if i == m - 1 {
+            let mut tiles = vec![(i, j)];
+            let mut tile = (i, j);
+            while let Some(before) = previous[tile.0 * n + tile.1] {
+                tiles.push(before);
+                tile = before;
+            }
+            tiles.reverse();
+            return Ok(Some(Route { cost, tiles }));
+        }
+        for (down, right) in &moves.0 {
+            let row = i as isize + down;
+            if row < 0 || row >= m as isize {
+                continue;
+            }
+            let (row, column) = (
+                row as usize,
+                (j as isize + right).rem_euclid(n as isize) as usize,
+            );
+            let next = cost.checked_add(map.tile_at(row, column).cost);
+            match next.and_then(|next| Some((next, priority(next, row)?))) {
+                Some((next, priority))
+                    if costs[row * n + column].is_none_or(|best| next < best) =>
+                {
+                    costs[row * n + column] = Some(next);
+                    previous[row * n + column] = Some((i, j));
+                    queue.push(Reverse((priority, next, row, column)));
+                }
+                Some(_) => (),
+                None => overflowed = true,
+            }
+        }
+    }
+
+    if overflowed {
+        return Err(AocError::overflow(
+            DAY,
+            "every route to the bottom costs more than a u64 holds",
+        ));
+    }
+    Ok(None)
+}
+
+/// A map of a single row is crossed by standing on its cheapest tile.
+fn top_row_route(map: &Map) -> Option<Route> {
+    (0..map.n)
+        .map(|j| Route {
+            cost: map.tile_at(0, j).cost,
+            tiles: vec![(0, j)],
+        })
+        .min_by_key(|r| r.cost)
+}
+
+/// The map repeated to the right as far as `cells` reach, with every one of
+/// them marked `O` where hitting it is free and `X` elsewhere, like in the
+/// puzzle statement. Cells are `(row, column)`, as yielded by `Map::trace`.
+pub fn render_text(map: &Map, cells: impl IntoIterator<Item = (usize, usize)>) -> String {
+    let mut rows: Vec<Vec<char>> = map.grid.clone();
+    for (i, j) in cells {
+        if i >= map.m {
+            continue;
+        }
+        let row = &mut rows[i];
+        while row.len() <= j {
+            row.extend_from_slice(&map.grid[i]);
+        }
+        row[j] = if map.tile_at(i, j).cost == 0 {
+            'O'
+        } else {
+            'X'
+        };
+    }
+
+    // Every row is as wide as the widest one, repeating the map as a whole
+    let copies = rows.iter().map(|r| r.len()).max().unwrap_or(0) / map.n.max(1);
+    let mut text = String::new();
+    for (i, row) in rows.iter_mut().enumerate() {
+        while row.len() < copies * map.n {
+            row.extend_from_slice(&map.grid[i]);
+        }
+        text.extend(row.iter());
+        text.push('\n');
+    }
+
+    text
+}
+
+/// Colors given to the tiles of a legend in order, and to the slopes drawn
+/// over them.
+const TILE_COLORS: [&str; 5] = ["#ffffff", "#2e7d32", "#90a4ae", "#8d6e63", "#e3f2fd"];
+const SLOPE_COLORS: [&str; 6] = [
+    "#d62728", "#1f77b4", "#ff7f0e", "#9467bd", "#17becf", "#e377c2",
+];
+
+/// Side of a tile in SVG units.
+const CELL: usize = 10;
+
+/// `text` with the characters XML gives a meaning to escaped, as tile names
+/// come from the legend.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// SVG drawing of the map repeated as far as the widest of `slopes` goes,
+/// with the trace of each of them in a different color. Tiles that cost
+/// something are circled along the way.
+pub fn render_svg(map: &Map, slopes: &[Slope]) -> Result<String, AocError> {
+    let traces: Vec<Vec<((usize, usize), char)>> =
+        slopes.iter().map(|s| map.trace(*s).collect()).collect();
+    let widest = traces
+        .iter()
+        .flatten()
+        .map(|((_, j), _)| j + 1)
+        .max()
+        .unwrap_or(0);
+    let width = widest.div_ceil(map.n).max(1) * map.n * CELL;
+    let height = map.m * CELL;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+        w = width,
+        h = height
+    );
+    // The map is drawn once and repeated as a pattern
+    svg += &format!(
+        "<defs><pattern id=\"map\" width=\"{}\" height=\"{}\" patternUnits=\"userSpaceOnUse\">\n",
+        map.n * CELL,
+        height
+    );
+    // Tiles of the first kind are left as the background
+    svg += &format!(
+        "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+        map.n * CELL,
+        height,
+        TILE_COLORS[0]
+    );
+    for (i, row) in map.grid.iter().enumerate() {
+        for (j, symbol) in row.iter().enumerate() {
+            let kind = map
+                .legend
+                .tiles
+                .iter()
+                .position(|(s, _)| s == symbol)
+                .expect("maps only hold characters of their legend");
+            if kind == 0 {
+                continue;
+            }
+            svg += &format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{c}\" height=\"{c}\" fill=\"{}\"/>\n",
+                j * CELL,
+                i * CELL,
+                TILE_COLORS[kind % TILE_COLORS.len()],
+                c = CELL
+            );
+        }
+    }
+    svg += "</pattern></defs>\n";
+    svg += &format!(
+        "<rect width=\"{}\" height=\"{}\" fill=\"url(#map)\"/>\n",
+        width, height
+    );
+
+    let center = |k: usize| k * CELL + CELL / 2;
+    for (k, (slope, trace)) in slopes.iter().zip(&traces).enumerate() {
+        let color = SLOPE_COLORS[k % SLOPE_COLORS.len()];
+        let points: Vec<String> = trace
+            .iter()
+            .map(|((i, j), _)| format!("{},{}", center(*j), center(*i)))
+            .collect();
+        svg += &format!(
+            "<g stroke=\"{}\" fill=\"none\"><title>{}: {}</title>\n<polyline points=\"{}\"/>\n",
+            color,
+            slope,
+            escape(&map.breakdown(*slope)?.to_string()),
+            points.join(" ")
+        );
+        for ((i, j), _) in trace {
+            if map.tile_at(*i, *j).cost > 0 {
+                svg += &format!(
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\"/>\n",
+                    center(*j),
+                    center(*i),
+                    CELL / 2
+                );
+            }
+        }
+        svg += "</g>\n";
+    }
+    svg += "</svg>\n";
+
+    Ok(svg)
+}
+
+/// Parses the toboggan map, `#` being a tree and `.` open ground.
 #[aoc_generator(day3)]
-fn parse_map(input: &str) -> Map {
+pub fn parse_map(input: &str) -> Result<Map, AocError> {
+    parse_map_with(input, Legend::default())
+}
+
+/// Parses a map drawn with the characters of `legend`.
+pub fn parse_map_with(input: &str, legend: Legend) -> Result<Map, AocError> {
     let mut grid: Grid = Default::default();
-    input.lines().for_each(|line| {
-        let values = line.chars().collect();
+    for (i, line) in input.lines().enumerate() {
+        let values: Vec<char> = line.chars().collect();
+        if let Some(j) = values.iter().position(|c| legend.get(*c).is_none()) {
+            let text = values[j].to_string();
+            return Err(AocError::parse(DAY, i + 1, j + 1, &text, legend.expected()));
+        }
+        if let Some(first) = grid.first() {
+            if first.len() != values.len() {
+                let reason = format!("expected a row of {} tiles", first.len());
+                return Err(AocError::parse(DAY, i + 1, 1, line, reason));
+            }
+        }
         grid.push(values);
-    });
+    }
 
     let m = grid.len();
-    let n = grid[0].len();
+    let n = grid.first().map_or(0, |row| row.len());
+    if n == 0 {
+        return Err(AocError::parse(DAY, 1, 1, input, "the map is empty"));
+    }
 
-    Map { grid, n, m }
+    Ok(Map { grid, n, m, legend })
 }
 
-fn count_trees_slope(map: &Map, slope: (usize, usize)) -> usize {
-    let mut count = 0;
-    let mut i = 0;
-    let mut j = 0;
-    for _x in (0..map.m).step_by(slope.1) {
-        match map.get_char_at(i, j) {
-            '.' => (),
-            '#' => count += 1,
-            _ => panic!("This is not tupposed to happen!"),
-        }
-        i = i + slope.1;
-        j = j + slope.0;
-    }
-    return count;
+/// Trees hit going down `slope`.
+pub fn count_trees_slope(map: &Map, slope: Slope) -> usize {
+    map.trace(slope).filter(|(_, tile)| *tile == '#').count()
 }
 
+fn slope(right: usize, down: usize) -> Slope {
+    Slope::new(right, down).expect("puzzle slopes move down")
+}
+
+/// Counts the trees hit following the (3, 1) slope.
 #[aoc(day3, part1)]
-fn count_trees(map: &Map) -> usize {
-    return count_trees_slope(map, (3, 1));
+pub fn count_trees(map: &Map) -> usize {
+    count_trees_slope(map, slope(3, 1))
 }
 
+/// Product of the trees hit on every slope of the puzzle.
 #[aoc(day3, part2)]
-fn part_2(map: &Map) -> usize {
+pub fn part_2(map: &Map) -> usize {
     let slopes = [(1usize, 1usize), (3, 1), (5, 1), (7, 1), (1, 2)];
 
     slopes
         .iter()
-        .map(|s| count_trees_slope(map, *s))
+        .map(|(right, down)| count_trees_slope(map, slope(*right, *down)))
         .fold(1, |acc, x| acc * x)
 }
 
+/// Solver for day 3, usable outside of cargo-aoc.
+pub struct Day03;
+
+impl Solver for Day03 {
+    type Input = Map;
+    type Output = usize;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        parse_map(input)
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(count_trees(input))
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(part_2(input))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
     const TEST_INPUT: &str = "..##.......\n#...#...#..\n.#....#..#.\n..#.#...#.#\n.#...##..#.\n..#.##.....\n.#.#.#....#\n.#........#\n#.##...#...\n#...##....#\n.#..#...#.#";
     #[test]
     fn test_parse_map() {
-        let map = parse_map(TEST_INPUT);
+        let map = parse_map(TEST_INPUT).unwrap();
         assert_eq!(map.n, 11);
         assert_eq!(map.m, 11);
     }
     #[test]
     fn test_example_p1() {
-        let map = parse_map(TEST_INPUT);
+        let map = parse_map(TEST_INPUT).unwrap();
         assert_eq!(count_trees(&map), 7);
     }
 
     #[test]
     fn test_get_char() {
-        let map = parse_map(TEST_INPUT);
+        let map = parse_map(TEST_INPUT).unwrap();
         assert_eq!(map.get_char_at(0, 13), '#');
         assert_eq!(map.get_char_at(0, 15), '.');
     }
 
     #[test]
     fn test_count_slope() {
-        let map = parse_map(TEST_INPUT);
-        assert_eq!(count_trees_slope(&map, (3, 1)), 7);
-        assert_eq!(count_trees_slope(&map, (1, 2)), 2);
+        let map = parse_map(TEST_INPUT).unwrap();
+        assert_eq!(count_trees_slope(&map, slope(3, 1)), 7);
+        assert_eq!(count_trees_slope(&map, slope(1, 2)), 2);
+    }
+
+    #[test]
+    fn test_trace() {
+        let map = parse_map(TEST_INPUT).unwrap();
+        let visited: Vec<((usize, usize), char)> = map.trace(slope(3, 1)).take(4).collect();
+        assert_eq!(
+            visited,
+            vec![((0, 0), '.'), ((1, 3), '.'), ((2, 6), '#'), ((3, 9), '.')]
+        );
+        assert_eq!(map.trace(slope(1, 2)).count(), 6);
+        assert_eq!(map.trace(slope(3, 1)).last(), Some(((10, 30), '#')));
+
+        // Half a column per row moves every other row
+        let half: Slope = "1/2,1".parse().unwrap();
+        let columns: Vec<usize> = map.trace(half).map(|((_, j), _)| j).take(5).collect();
+        assert_eq!(columns, vec![0, 0, 1, 1, 2]);
+        assert_eq!("2/4,1".parse::<Slope>(), Ok(half));
+        assert_eq!(half.to_string(), "1/2,1");
+        assert_eq!("6/2,1".parse::<Slope>().unwrap().to_string(), "3,1");
+        assert!("3,0".parse::<Slope>().is_err());
+        assert!("1/0,1".parse::<Slope>().is_err());
+        assert!("3".parse::<Slope>().is_err());
+    }
+
+    #[test]
+    fn test_best_slope() {
+        let map = parse_map(TEST_INPUT).unwrap();
+        let puzzle = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)].map(|(r, d)| slope(r, d));
+        assert_eq!(
+            best_slope(&map, puzzle, Goal::Fewest),
+            Ok(Some((slope(1, 1), 2)))
+        );
+        assert_eq!(
+            best_slope(&map, puzzle, Goal::Most),
+            Ok(Some((slope(3, 1), 7)))
+        );
+        assert_eq!(best_slope(&map, vec![], Goal::Most), Ok(None));
+
+        let slopes = Slope::all(3, 2, 2);
+        assert_eq!(slopes.len(), 2 * (4 + 3));
+        let (fewest, trees) = best_slope(&map, slopes.clone(), Goal::Fewest)
+            .unwrap()
+            .unwrap();
+        assert_eq!(count_trees_slope(&map, fewest) as u64, trees);
+        assert!(slopes
+            .iter()
+            .all(|s| count_trees_slope(&map, *s) as u64 >= trees));
+    }
+
+    #[test]
+    fn test_cheapest_route() {
+        let map = parse_map(TEST_INPUT).unwrap();
+        let route = cheapest_route(&map, &Moves::king()).unwrap().unwrap();
+        // There's a way through open ground only
+        assert_eq!(route.cost, 0);
+        assert_eq!(route.tiles.len(), 11);
+        assert_eq!(route.tiles[0].0, 0);
+        assert_eq!(route.tiles[10].0, 10);
+        assert!(route
+            .tiles
+            .iter()
+            .all(|(i, j)| map.get_char_at(*i, *j) == '.'));
+        for pair in route.tiles.windows(2) {
+            let (down, right) = (
+                pair[1].0 as isize - pair[0].0 as isize,
+                pair[1].1 as isize - pair[0].1 as isize,
+            );
+            assert!(down.abs() <= 1 && (right.abs() <= 1 || right.abs() == 10));
+        }
+
+        // Trees in every column of a row can't be avoided
+        let map = parse_map(".#.\n###\n#.#").unwrap();
+        assert_eq!(
+            cheapest_route(&map, &Moves::right_down())
+                .unwrap()
+                .unwrap()
+                .cost,
+            2
+        );
+        assert_eq!(
+            cheapest_route(&map, &Moves::king()).unwrap().unwrap().cost,
+            1
+        );
+        let map = parse_map("#.#\n###\n...").unwrap();
+        assert_eq!(
+            cheapest_route(&map, &Moves::right_down()),
+            Ok(Some(Route {
+                cost: 1,
+                tiles: vec![(0, 1), (1, 1), (2, 1)]
+            }))
+        );
+        // Wrapping around is cheaper than walking through the tree
+        let map = parse_map(".###\n.##.\n##..").unwrap();
+        let route = cheapest_route(&map, &"1,0;0,-1".parse().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(route.cost, 0);
+        assert_eq!(route.tiles, vec![(0, 0), (1, 0), (1, 3), (2, 3)]);
+
+        assert_eq!(cheapest_route(&map, &"0,1".parse().unwrap()), Ok(None));
+        assert!("0,0".parse::<Moves>().is_err());
+        assert!("1;0".parse::<Moves>().is_err());
+    }
+
+    #[test]
+    fn test_route_overflow() {
+        let near = u64::MAX - 1;
+        let legend = Legend::default().with('#', "tree", near);
+        // Going through the trees overflows, but going around them doesn't
+        let map = parse_map_with(".#\n.#\n.#", legend.clone()).unwrap();
+        let route = cheapest_route(&map, &Moves::right_down()).unwrap().unwrap();
+        assert_eq!(route.cost, 0);
+        assert_eq!(route.tiles, vec![(0, 0), (1, 0), (2, 0)]);
+
+        // A route costing exactly `u64::MAX` still fits
+        let map = parse_map_with("#\n.", legend.clone().with('.', "open", 1)).unwrap();
+        let route = cheapest_route(&map, &Moves::right_down()).unwrap();
+        assert_eq!(route.map(|r| r.cost), Some(u64::MAX));
+
+        let map = parse_map_with("#\n#", legend.clone()).unwrap();
+        assert!(matches!(
+            cheapest_route(&map, &Moves::king()),
+            Err(AocError::Overflow { day: 3, .. })
+        ));
+        // Even estimating the rows left overflows
+        let map = parse_map_with("#\n#\n#", Legend::empty().with('#', "tree", near / 2)).unwrap();
+        assert!(cheapest_route(&map, &Moves::right_down()).is_err());
     }
+
+    #[test]
+    fn test_render_text() {
+        let map = parse_map(TEST_INPUT).unwrap();
+        let cells = map.trace(slope(3, 1)).map(|(cell, _)| cell);
+        // As drawn in the puzzle statement, with the start marked too
+        let expected = [
+            "O.##.........##.........##.......",
+            "#..O#...#..#...#...#..#...#...#..",
+            ".#....X..#..#....#..#..#....#..#.",
+            "..#.#...#O#..#.#...#.#..#.#...#.#",
+            ".#...##..#..X...##..#..#...##..#.",
+            "..#.##.......#.X#.......#.##.....",
+            ".#.#.#....#.#.#.#.O..#.#.#.#....#",
+            ".#........#.#........X.#........#",
+            "#.##...#...#.##...#...#.X#...#...",
+            "#...##....##...##....##...#X....#",
+            ".#..#...#.#.#..#...#.#.#..#...X.#",
+        ];
+        assert_eq!(render_text(&map, cells), expected.join("\n") + "\n");
+
+        let route = cheapest_route(&map, &Moves::right_down()).unwrap().unwrap();
+        let text = render_text(&map, route.tiles.clone());
+        assert_eq!(text.lines().next().map(str::len), Some(11));
+        assert_eq!(text.matches(['O', 'X']).count(), route.tiles.len());
+        assert_eq!(text.matches('X').count() as u64, route.cost);
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let map = parse_map(TEST_INPUT).unwrap();
+        let svg = render_svg(&map, &[slope(3, 1), slope(1, 2)]).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("width=\"330\" height=\"110\""));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert!(svg.contains("<title>3,1: open: 4 (0), tree: 7 (7), total 7</title>"));
+        // One circle per tree hit, 7 going 3,1 and 2 going 1,2
+        assert_eq!(svg.matches("<circle").count(), 9);
+        assert!(svg.trim_end().ends_with("</svg>"));
+
+        let legend: Legend = ".=a&b:0,#=<rock \"big\">:1".parse().unwrap();
+        let map = parse_map_with(TEST_INPUT, legend).unwrap();
+        let svg = render_svg(&map, &[slope(3, 1)]).unwrap();
+        assert!(svg.contains(
+            "<title>3,1: &lt;rock &quot;big&quot;&gt;: 7 (7), a&amp;b: 4 (0), total 7</title>"
+        ));
+        assert!(!svg.contains("<rock"));
+    }
+
+    /// Cost of the cheapest route, relaxing every tile until nothing changes.
+    fn reference_cost(map: &Map, moves: &Moves) -> Option<u64> {
+        let (m, n) = (map.height(), map.width());
+        let mut costs = vec![vec![None; n]; m];
+        costs[0] = (0..n).map(|j| Some(map.tile_at(0, j).cost)).collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for i in 0..m {
+                for j in 0..n {
+                    let cost = match costs[i][j] {
+                        Some(cost) => cost,
+                        None => continue,
+                    };
+                    for (down, right) in &moves.0 {
+                        let row = i as isize + down;
+                        if row < 0 || row >= m as isize {
+                            continue;
+                        }
+                        let column = (j as isize + right).rem_euclid(n as isize) as usize;
+                        let next = cost + map.tile_at(row as usize, column).cost;
+                        let current = &mut costs[row as usize][column];
+                        if current.is_none_or(|c| next < c) {
+                            *current = Some(next);
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+        costs[m - 1].iter().flatten().min().copied()
+    }
+
+    proptest! {
+        #[test]
+        fn test_route_matches_reference(
+            rows in proptest::collection::vec("[.#^]{5}", 1..6),
+            rock in 0u64..4,
+            king in any::<bool>(),
+        ) {
+            let legend = Legend::default().with('^', "rock", rock);
+            let map = parse_map_with(&rows.join("\n"), legend).unwrap();
+            let moves = if king { Moves::king() } else { Moves::right_down() };
+            let route = cheapest_route(&map, &moves).unwrap();
+            prop_assert_eq!(route.as_ref().map(|r| r.cost), reference_cost(&map, &moves));
+            if let Some(route) = route {
+                let cost: u64 = route.tiles.iter().map(|(i, j)| map.tile_at(*i, *j).cost).sum();
+                prop_assert_eq!(cost, route.cost);
+            }
+        }
+    }
+
+    #[test]
+    fn test_legend() {
+        let legend: Legend = ".=open:0,#=tree:1,^=rock:5,*=snow:2".parse().unwrap();
+        assert_eq!(legend.to_string(), ".=open:0,#=tree:1,^=rock:5,*=snow:2");
+        assert_eq!(Legend::default().to_string().parse(), Ok(Legend::default()));
+        assert!("#=tree".parse::<Legend>().is_err());
+        assert!("#=tree:1,#=rock:2".parse::<Legend>().is_err());
+        assert!("#tree:1".parse::<Legend>().is_err());
+
+        let map = parse_map_with(".^#\n*.^\n#*.", legend.clone()).unwrap();
+        assert_eq!(map.tile_at(0, 4).name, "rock");
+        let breakdown = map.breakdown(slope(1, 1)).unwrap();
+        assert_eq!(breakdown.hits("open"), 3);
+        assert_eq!(breakdown.total(), Ok(0));
+        let breakdown = map.breakdown(slope(1, 2)).unwrap();
+        assert_eq!(breakdown.to_string(), "open: 1 (0), snow: 1 (2), total 2");
+        assert_eq!(
+            best_slope(&map, Slope::all(2, 1, 1), Goal::Most),
+            Ok(Some((slope(2, 1), 7)))
+        );
+
+        assert_eq!(
+            parse_map_with(".^#\n*.~", legend).err(),
+            Some(AocError::parse(
+                3,
+                2,
+                3,
+                "~",
+                "expected one of `.`, `#`, `^`, `*`"
+            ))
+        );
+    }
+    #[test]
+    fn test_breakdown_overflow() {
+        let legend = Legend::default().with('#', "tree", u64::MAX - 1);
+        let map = parse_map_with("#\n#\n.", legend.clone()).unwrap();
+        assert!(matches!(
+            map.breakdown(slope(0, 1)),
+            Err(AocError::Overflow { day: 3, .. })
+        ));
+        assert!(best_slope(&map, Slope::all(1, 1, 2), Goal::Fewest).is_err());
+        assert!(render_svg(&map, &[slope(0, 1)]).is_err());
+
+        // Going two rows down hits a single tree, right at the limit
+        let breakdown = map.breakdown(slope(0, 2)).unwrap();
+        assert_eq!(breakdown.total(), Ok(u64::MAX - 1));
+        let map = parse_map_with("#\n.", legend.with('.', "open", 1)).unwrap();
+        assert_eq!(map.breakdown(slope(0, 1)).unwrap().total(), Ok(u64::MAX));
+
+        let mut breakdown = Breakdown::default();
+        breakdown.tiles.insert("rock".to_string(), (1, u64::MAX));
+        breakdown.tiles.insert("tree".to_string(), (1, 1));
+        assert!(breakdown.total().is_err());
+        assert_eq!(
+            breakdown.to_string(),
+            format!(
+                "rock: 1 ({}), tree: 1 (1), total over {}",
+                u64::MAX,
+                u64::MAX
+            )
+        );
+    }
+
     #[test]
     fn test_example_p2() {
-        let map = parse_map(TEST_INPUT);
+        let map = parse_map(TEST_INPUT).unwrap();
         assert_eq!(part_2(&map), 336);
     }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_map("..#\n.O#").err(),
+            Some(AocError::parse(3, 2, 2, "O", "expected `.` or `#`"))
+        );
+        assert_eq!(
+            parse_map("..#\n.#").err(),
+            Some(AocError::parse(3, 2, 1, ".#", "expected a row of 3 tiles"))
+        );
+        assert!(parse_map("").is_err());
+    }
 }
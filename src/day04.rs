@@ -1,250 +1,967 @@
+use crate::error::column;
+use crate::{AocError, Solver};
 use aoc_runner_derive::{aoc, aoc_generator};
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::error::Error;
+use serde::de::{self, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
 
-#[allow(non_camel_case_types)]
-type year = String;
+const DAY: u32 = 4;
 
-#[allow(non_camel_case_types)]
-type hex = String;
-
-struct Passport {
-    birth_year: Option<year>,
-    issue_year: Option<year>,
-    expiration_year: Option<year>,
+/// Passport fields as found in the batch file, `None` when missing. See
+/// `TypedPassport` for their parsed values.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Passport {
+    birth_year: Option<String>,
+    issue_year: Option<String>,
+    expiration_year: Option<String>,
     height: Option<String>,
-    hair_color: Option<hex>,
+    hair_color: Option<String>,
     eye_color: Option<String>,
     passport_id: Option<String>,
     country_id: Option<String>,
+    /// Keys of the present fields, line by line as they were written.
+    layout: Vec<Vec<&'static str>>,
 }
 
-#[derive(Debug, PartialEq)]
-enum Height {
-    CM(u64),
-    IN(u64),
-    NONE,
+lazy_static! {
+    static ref DEFAULT_SCHEMA: Schema = Schema::default();
 }
 
-fn parse_height(h: &str) -> Height {
-    lazy_static! {
-        static ref CM_MATCHER: Regex = Regex::new(r"(?P<cm>\d+)cm").unwrap();
-        static ref IN_MATCHER: Regex = Regex::new(r"(?P<in>\d+)in").unwrap();
-    }
+/// Units a height may be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LengthUnit {
+    Mm,
+    Cm,
+    In,
+    Ft,
+}
 
-    let mut ret = Height::NONE;
+impl LengthUnit {
+    pub const ALL: [LengthUnit; 4] = [
+        LengthUnit::Mm,
+        LengthUnit::Cm,
+        LengthUnit::In,
+        LengthUnit::Ft,
+    ];
 
-    match CM_MATCHER.captures(h) {
-        Some(v) => {
-            ret = Height::CM(v.name("cm").unwrap().parse().unwrap());
+    /// Micrometres in one of the unit, so every unit is a whole number of
+    /// them.
+    fn micrometres(self) -> u64 {
+        match self {
+            LengthUnit::Mm => 1_000,
+            LengthUnit::Cm => 10_000,
+            LengthUnit::In => 25_400,
+            LengthUnit::Ft => 304_800,
         }
-        None => (),
     }
 
-    match IN_MATCHER.captures(h) {
-        Some(v) => {
-            ret = Height::IN(v.name("in").unwrap().parse().unwrap());
+    fn symbol(self) -> &'static str {
+        match self {
+            LengthUnit::Mm => "mm",
+            LengthUnit::Cm => "cm",
+            LengthUnit::In => "in",
+            LengthUnit::Ft => "ft",
         }
-        None => (),
     }
+}
 
-    return ret;
+impl fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
 }
 
-#[aoc_generator(day4)]
-fn parse_input(input: &str) -> Result<Vec<Passport>, Box<dyn Error>> {
-    separate_input(input)?
-        .iter()
-        .map(|row| parse_passport(row))
-        .collect()
+impl FromStr for LengthUnit {
+    type Err = AocError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LengthUnit::ALL
+            .iter()
+            .find(|u| u.symbol() == s)
+            .copied()
+            .ok_or_else(|| {
+                AocError::invalid_argument(format!(
+                    "unknown unit `{}`, expected mm, cm, in or ft",
+                    s
+                ))
+            })
+    }
 }
 
-#[aoc(day4, part1)]
-fn part_1(passport_list: &[Passport]) -> usize {
-    passport_list.iter().filter(|p| valid_passport(p)).count()
+/// A whole number of some unit, written like `183cm`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Height {
+    pub value: u64,
+    pub unit: LengthUnit,
 }
 
-#[aoc(day4, part2)]
-fn part_2(passport_list: &[Passport]) -> usize {
-    passport_list
-        .iter()
-        .filter(|p| valid_passport_p2(p))
-        .count()
+impl Height {
+    pub fn new(value: u64, unit: LengthUnit) -> Height {
+        Height { value, unit }
+    }
+
+    /// The height in micrometres, saturating for heights no passport has.
+    pub fn micrometres(&self) -> u64 {
+        self.value.saturating_mul(self.unit.micrometres())
+    }
+
+    /// The height in micrometres, which always fits in a `u128`.
+    fn exact_micrometres(&self) -> u128 {
+        self.value as u128 * self.unit.micrometres() as u128
+    }
+
+    /// The height in `unit`, fractions included.
+    pub fn in_unit(&self, unit: LengthUnit) -> f64 {
+        self.exact_micrometres() as f64 / unit.micrometres() as f64
+    }
+
+    /// The height in `unit`, rounded to the nearest whole one and saturating
+    /// when that doesn't fit in a `u64`.
+    pub fn to(&self, unit: LengthUnit) -> Height {
+        let per_unit = unit.micrometres() as u128;
+        let value = (self.exact_micrometres() + per_unit / 2) / per_unit;
+        Height::new(u64::try_from(value).unwrap_or(u64::MAX), unit)
+    }
 }
 
-fn valid_passport(passport: &Passport) -> bool {
-    if passport.birth_year.is_none()
-        || passport.issue_year.is_none()
-        || passport.expiration_year.is_none()
-        || passport.height.is_none()
-        || passport.hair_color.is_none()
-        || passport.eye_color.is_none()
-        || passport.passport_id.is_none()
-    {
-        return false;
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.value, self.unit)
     }
+}
+
+impl FromStr for Height {
+    type Err = AocError;
 
-    return true;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (value, unit) = s.split_at(split);
+        let invalid =
+            || AocError::invalid_argument(format!("expected a height like `183cm`, found `{}`", s));
+        let value = parse_number(value).map_err(|_| invalid())?;
+        let unit: LengthUnit = unit.parse().map_err(|_| invalid())?;
+        value
+            .checked_mul(unit.micrometres())
+            .ok_or_else(|| AocError::invalid_argument(format!("height too large: `{}`", s)))?;
+
+        Ok(Height::new(value, unit))
+    }
 }
 
-fn valid_passport_p2(passport: &Passport) -> bool {
-    lazy_static! {
-        static ref HC_MATCHER: Regex = Regex::new(r"^#[0-9a-f]{6}$").unwrap();
-        static ref PID_MATCHER: Regex = Regex::new(r"^\d{9}$").unwrap();
+/// An RGB color, written `#rrggbb`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
     }
+}
+
+impl FromStr for Color {
+    type Err = AocError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s
+            .strip_prefix('#')
+            .filter(|h| h.len() == 6 && h.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| {
+                AocError::invalid_argument(format!(
+                    "expected a color like `#a97842`, found `{}`",
+                    s
+                ))
+            })?;
+        let channel = |at: usize| u8::from_str_radix(&hex[at..at + 2], 16).unwrap();
 
-    if !valid_passport(passport) {
-        return false;
+        Ok(Color {
+            red: channel(0),
+            green: channel(2),
+            blue: channel(4),
+        })
     }
-    if let Some(y) = &passport.birth_year {
-        let parsed_year: usize = y.parse().unwrap();
-        if y.to_string().chars().count() != 4 || !(parsed_year >= 1920 && parsed_year <= 2002) {
-            return false;
+}
+
+/// Eye colors a passport may list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EyeColor {
+    Amber,
+    Blue,
+    Brown,
+    Gray,
+    Green,
+    Hazel,
+    Other,
+}
+
+impl EyeColor {
+    pub const ALL: [EyeColor; 7] = [
+        EyeColor::Amber,
+        EyeColor::Blue,
+        EyeColor::Brown,
+        EyeColor::Gray,
+        EyeColor::Green,
+        EyeColor::Hazel,
+        EyeColor::Other,
+    ];
+
+    /// Three letter code of the color in passports.
+    pub fn code(self) -> &'static str {
+        match self {
+            EyeColor::Amber => "amb",
+            EyeColor::Blue => "blu",
+            EyeColor::Brown => "brn",
+            EyeColor::Gray => "gry",
+            EyeColor::Green => "grn",
+            EyeColor::Hazel => "hzl",
+            EyeColor::Other => "oth",
         }
     }
-    if let Some(y) = &passport.issue_year {
-        let parsed_year: usize = y.parse().unwrap();
-        if y.to_string().chars().count() != 4 || !(parsed_year >= 2010 && parsed_year <= 2020) {
-            return false;
+}
+
+impl fmt::Display for EyeColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for EyeColor {
+    type Err = AocError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EyeColor::ALL
+            .iter()
+            .find(|c| c.code() == s)
+            .copied()
+            .ok_or_else(|| {
+                let codes: Vec<&str> = EyeColor::ALL.iter().map(|c| c.code()).collect();
+                AocError::invalid_argument(format!(
+                    "unknown eye color `{}`, expected one of {}",
+                    s,
+                    codes.join(", ")
+                ))
+            })
+    }
+}
+
+/// A passport with every field parsed into its type, built from a
+/// `Passport` with `TryFrom`. Bounds are left to a `Schema` to check.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypedPassport {
+    pub birth_year: u32,
+    pub issue_year: u32,
+    pub expiration_year: u32,
+    pub height: Height,
+    pub hair_color: Color,
+    pub eye_color: EyeColor,
+    /// Kept as text, as leading zeros count.
+    pub passport_id: String,
+    pub country_id: Option<String>,
+}
+
+impl TryFrom<&Passport> for TypedPassport {
+    type Error = Violation;
+
+    fn try_from(passport: &Passport) -> Result<Self, Self::Error> {
+        let year = |key: &str| {
+            let value = required(passport, key)?;
+            parse_number(value)
+                .and_then(|y| u32::try_from(y).map_err(|_| "year too large".to_string()))
+                .map_err(|reason| violation(key, value, reason))
+        };
+
+        Ok(TypedPassport {
+            birth_year: year("byr")?,
+            issue_year: year("iyr")?,
+            expiration_year: year("eyr")?,
+            height: typed(passport, "hgt")?,
+            hair_color: typed(passport, "hcl")?,
+            eye_color: typed(passport, "ecl")?,
+            passport_id: required(passport, "pid")?.to_string(),
+            country_id: passport.country_id.clone(),
+        })
+    }
+}
+
+fn required<'a>(passport: &'a Passport, key: &str) -> Result<&'a str, Violation> {
+    passport.field(key).ok_or_else(|| Violation {
+        key: key.to_string(),
+        value: None,
+        reason: "missing".to_string(),
+    })
+}
+
+fn typed<T: FromStr<Err = AocError>>(passport: &Passport, key: &str) -> Result<T, Violation> {
+    let value = required(passport, key)?;
+    value.parse().map_err(|e| {
+        let reason = match e {
+            AocError::InvalidArgument(reason) => reason,
+            e => e.to_string(),
+        };
+        violation(key, value, reason)
+    })
+}
+
+fn violation(key: &str, value: &str, reason: String) -> Violation {
+    Violation {
+        key: key.to_string(),
+        value: Some(value.to_string()),
+        reason,
+    }
+}
+
+/// Written like a record of the batch, `cid` last when there's one.
+impl fmt::Display for TypedPassport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "byr:{} iyr:{} eyr:{} hgt:{} hcl:{} ecl:{} pid:{}",
+            self.birth_year,
+            self.issue_year,
+            self.expiration_year,
+            self.height,
+            self.hair_color,
+            self.eye_color,
+            self.passport_id
+        )?;
+        if let Some(cid) = &self.country_id {
+            write!(f, " cid:{}", cid)?;
+        }
+        Ok(())
+    }
+}
+
+/// Text fields of a document, looked up by key.
+pub trait Document {
+    fn field(&self, key: &str) -> Option<&str>;
+}
+
+impl Document for Passport {
+    fn field(&self, key: &str) -> Option<&str> {
+        let value = match key {
+            "byr" => &self.birth_year,
+            "iyr" => &self.issue_year,
+            "eyr" => &self.expiration_year,
+            "hgt" => &self.height,
+            "hcl" => &self.hair_color,
+            "ecl" => &self.eye_color,
+            "pid" => &self.passport_id,
+            "cid" => &self.country_id,
+            _ => return None,
+        };
+        value.as_deref()
+    }
+}
+
+impl Passport {
+    fn value_mut(&mut self, key: &str) -> Option<&mut Option<String>> {
+        match key {
+            "byr" => Some(&mut self.birth_year),
+            "iyr" => Some(&mut self.issue_year),
+            "eyr" => Some(&mut self.expiration_year),
+            "hgt" => Some(&mut self.height),
+            "hcl" => Some(&mut self.hair_color),
+            "ecl" => Some(&mut self.eye_color),
+            "pid" => Some(&mut self.passport_id),
+            "cid" => Some(&mut self.country_id),
+            _ => None,
+        }
+    }
+
+    /// Sets a field after the ones already there, on a line of its own if
+    /// `new_line`. Returns false, leaving the passport as is, for unknown
+    /// or repeated keys.
+    fn push(&mut self, key: &str, value: String, new_line: bool) -> bool {
+        let key = match KEYS.iter().find(|k| **k == key) {
+            Some(key) => *key,
+            None => return false,
+        };
+        match self.value_mut(key) {
+            Some(slot @ None) => *slot = Some(value),
+            _ => return false,
+        }
+        match self.layout.last_mut() {
+            Some(line) if !new_line => line.push(key),
+            _ => self.layout.push(vec![key]),
+        }
+        true
+    }
+
+    /// Keys of the present fields, in the order they were written.
+    fn keys(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.layout.iter().flatten().copied()
+    }
+}
+
+impl Serialize for Passport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.keys().count()))?;
+        for key in self.keys() {
+            map.serialize_entry(key, &self.field(key))?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Passport {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PassportVisitor;
+
+        impl<'de> Visitor<'de> for PassportVisitor {
+            type Value = Passport;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a map of passport fields")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Passport, A::Error> {
+                let mut passport = Passport::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    if !KEYS.contains(&key.as_str()) {
+                        return Err(de::Error::unknown_field(&key, &KEYS));
+                    }
+                    if passport.field(&key).is_some() {
+                        return Err(de::Error::custom(format!("duplicate field `{}`", key)));
+                    }
+                    passport.push(&key, map.next_value()?, false);
+                }
+                Ok(passport)
+            }
         }
+
+        deserializer.deserialize_map(PassportVisitor)
+    }
+}
+
+/// A `key:value` token of a record, and the 1-based position it starts at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub key: String,
+    pub value: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Tokens of a record, in the order they're written.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    pub tokens: Vec<Token>,
+}
+
+impl Record {
+    pub fn token(&self, key: &str) -> Option<&Token> {
+        self.tokens.iter().find(|t| t.key == key)
     }
-    if let Some(y) = &passport.expiration_year {
-        let parsed_year: usize = y.parse().unwrap();
-        if y.to_string().chars().count() != 4 || !(parsed_year >= 2020 && parsed_year <= 2030) {
-            return false;
+}
+
+impl Document for Record {
+    fn field(&self, key: &str) -> Option<&str> {
+        self.token(key).map(|t| t.value.as_str())
+    }
+}
+
+/// How `tokenize` treats records that aren't well formed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Skips malformed tokens and repeated keys, keeping the first value,
+    /// and keeps unknown keys, listing every problem along the way.
+    Lenient,
+    /// Fails on the first problem.
+    Strict,
+}
+
+impl FromStr for Mode {
+    type Err = AocError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lenient" => Ok(Mode::Lenient),
+            "strict" => Ok(Mode::Strict),
+            _ => Err(AocError::invalid_argument(format!(
+                "unknown mode `{}`, expected lenient or strict",
+                s
+            ))),
         }
     }
+}
 
-    if let Some(height) = &passport.height {
-        match parse_height(height) {
-            Height::IN(v) => {
-                if !(v >= 59 && v <= 76) {
-                    return false;
+/// Records of a batch, and the problems `Mode::Lenient` got past.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tokenized {
+    pub records: Vec<Record>,
+    pub issues: Vec<AocError>,
+}
+
+/// Splits every record of a blank line separated batch into `key:value`
+/// tokens. Keys missing from `schema` are unknown. Blank lines before the
+/// first record or after the last one don't make records of their own.
+pub fn tokenize(input: &str, schema: &Schema, mode: Mode) -> Result<Tokenized, AocError> {
+    let mut tokenized = Tokenized::default();
+    for lines in separate_input(input)?.into_iter().filter(|l| !l.is_empty()) {
+        let mut record = Record::default();
+        for (idx, line) in lines {
+            for text in line.split_whitespace() {
+                let issue = |reason: String| {
+                    AocError::parse(DAY, idx + 1, column(line, text), text, reason)
+                };
+                let issue = match text.split_once(':') {
+                    Some((key, value)) if !key.is_empty() && !value.is_empty() => {
+                        match record.token(key) {
+                            Some(first) => Some(issue(format!(
+                                "duplicate key, first at line {}, column {}",
+                                first.line, first.column
+                            ))),
+                            None => {
+                                record.tokens.push(Token {
+                                    key: key.to_string(),
+                                    value: value.to_string(),
+                                    line: idx + 1,
+                                    column: column(line, text),
+                                });
+                                match schema.field(key) {
+                                    Some(_) => None,
+                                    None => Some(issue("unknown key".to_string())),
+                                }
+                            }
+                        }
+                    }
+                    _ => Some(issue("expected `key:value`".to_string())),
+                };
+                if let Some(issue) = issue {
+                    if mode == Mode::Strict {
+                        return Err(issue);
+                    }
+                    tokenized.issues.push(issue);
                 }
             }
-            Height::CM(v) => {
-                if !(v >= 150 && v <= 193) {
-                    return false;
+        }
+        tokenized.records.push(record);
+    }
+
+    Ok(tokenized)
+}
+
+/// Fields a document is checked against, in order. Keys missing from the
+/// schema are not checked at all.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schema {
+    pub fields: Vec<Field>,
+}
+
+/// Rule the value of `key` must follow when present, and whether it must be.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Field {
+    pub key: String,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(flatten)]
+    pub rule: Rule,
+}
+
+/// Kinds of values a field may hold, written with a `type` key.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Rule {
+    /// A whole number of `digits` digits within `min..=max`, each bound
+    /// being optional.
+    Number {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        digits: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<u64>,
+    },
+    /// A whole number followed by one of `units`, like `183cm`.
+    Measure { units: Vec<Unit> },
+    /// Text matching `pattern`, which should be anchored to match it all.
+    Pattern {
+        #[serde(with = "regex_string")]
+        pattern: Regex,
+    },
+    /// One of `values`.
+    OneOf { values: Vec<String> },
+    /// Anything at all.
+    Any,
+}
+
+/// Unit of a `Rule::Measure` and the bounds of a number in it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Unit {
+    pub unit: String,
+    pub min: u64,
+    pub max: u64,
+}
+
+/// Reads and writes regexes as their pattern.
+mod regex_string {
+    use regex::Regex;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(regex: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(regex.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern).map_err(de::Error::custom)
+    }
+}
+
+impl Rule {
+    /// Whether `value` follows the rule, and why not.
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        match self {
+            Rule::Number { digits, min, max } => {
+                if let Some(digits) = digits {
+                    if value.len() != *digits {
+                        return Err(format!("expected {} digits", digits));
+                    }
                 }
+                let number = parse_number(value)?;
+                check_bounds(number, *min, *max)
+            }
+            Rule::Measure { units } => {
+                let split = value
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(value.len());
+                let (number, unit) = value.split_at(split);
+                let found = units.iter().find(|u| u.unit == unit).ok_or_else(|| {
+                    let units: Vec<&str> = units.iter().map(|u| u.unit.as_str()).collect();
+                    format!("expected a unit among {}", units.join(", "))
+                })?;
+                check_bounds(parse_number(number)?, Some(found.min), Some(found.max))
             }
-            Height::NONE => {
-                return false;
+            Rule::Pattern { pattern } if !pattern.is_match(value) => {
+                Err(format!("expected to match `{}`", pattern))
             }
+            Rule::OneOf { values } if !values.iter().any(|v| v == value) => {
+                Err(format!("expected one of {}", values.join(", ")))
+            }
+            _ => Ok(()),
         }
     }
+}
+
+fn parse_number(value: &str) -> Result<u64, String> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return Err("expected a number".to_string());
+    }
+    value.parse().map_err(|_| "number too large".to_string())
+}
+
+fn check_bounds(number: u64, min: Option<u64>, max: Option<u64>) -> Result<(), String> {
+    match (min, max) {
+        (Some(min), _) if number < min => Err(format!("expected at least {}", min)),
+        (_, Some(max)) if number > max => Err(format!("expected at most {}", max)),
+        _ => Ok(()),
+    }
+}
+
+impl Schema {
+    /// Rule of `key`, if the schema knows about it.
+    pub fn field(&self, key: &str) -> Option<&Field> {
+        self.fields.iter().find(|f| f.key == key)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Schema, AocError> {
+        toml::from_str(text).map_err(|e| AocError::invalid_argument(format!("schema toml: {}", e)))
+    }
+
+    pub fn from_json(text: &str) -> Result<Schema, AocError> {
+        serde_json::from_str(text)
+            .map_err(|e| AocError::invalid_argument(format!("schema json: {}", e)))
+    }
+
+    pub fn to_toml(&self) -> Result<String, AocError> {
+        toml::to_string(self).map_err(|e| AocError::invalid_argument(format!("schema toml: {}", e)))
+    }
 
-    if let Some(hair_color) = &passport.hair_color {
-        if !HC_MATCHER.is_match(hair_color) {
-            return false;
+    pub fn to_json(&self) -> Result<String, AocError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| AocError::invalid_argument(format!("schema json: {}", e)))
+    }
+
+    /// Whether `document` has every required field, whatever they hold.
+    pub fn has_required(&self, document: &impl Document) -> bool {
+        self.fields
+            .iter()
+            .all(|f| !f.required || document.field(&f.key).is_some())
+    }
+
+    /// Whether `document` has every required field and every field present
+    /// follows its rule.
+    pub fn is_valid(&self, document: &impl Document) -> bool {
+        self.validate(document).is_valid()
+    }
+
+    /// Every field of `document` missing or breaking its rule, in schema
+    /// order.
+    pub fn validate(&self, document: &impl Document) -> Validation {
+        let mut violations = vec![];
+        for field in &self.fields {
+            let value = document.field(&field.key);
+            let reason = match value {
+                Some(value) => field.rule.check(value).err(),
+                None if field.required => Some("missing".to_string()),
+                None => None,
+            };
+            if let Some(reason) = reason {
+                violations.push(Violation {
+                    key: field.key.clone(),
+                    value: value.map(str::to_string),
+                    reason,
+                });
+            }
         }
+
+        Validation { violations }
     }
+}
+
+/// A field missing from a document, or holding a value against its rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    pub key: String,
+    /// What the field holds, `None` when it's missing.
+    pub value: Option<String>,
+    pub reason: String,
+}
 
-    if let Some(passport_id) = &passport.passport_id {
-        if !PID_MATCHER.is_match(passport_id) {
-            return false;
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}: `{}`: {}", self.key, value, self.reason),
+            None => write!(f, "{}: {}", self.key, self.reason),
         }
     }
+}
 
-    if let Some(eye_color) = &passport.eye_color {
-        match eye_color.as_ref() {
-            "amb" => (),
-            "blu" => (),
-            "brn" => (),
-            "gry" => (),
-            "grn" => (),
-            "hzl" => (),
-            "oth" => (),
-            _ => {
-                return false;
-            }
+/// Outcome of checking a document against a schema.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Validation {
+    pub violations: Vec<Violation>,
+}
+
+impl Validation {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// How many documents of a batch were checked, and how many failed on each
+/// field.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub documents: usize,
+    pub valid: usize,
+    /// Documents failing on each key. A document counts once for every
+    /// field it fails on.
+    pub failures: BTreeMap<String, usize>,
+}
+
+impl Report {
+    /// Keys with the documents failing on them, the most failed first.
+    pub fn by_count(&self) -> Vec<(&str, usize)> {
+        let mut counts: Vec<(&str, usize)> = self
+            .failures
+            .iter()
+            .map(|(key, count)| (key.as_str(), *count))
+            .collect();
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        counts
+    }
+}
+
+/// Written as `2 of 4 document(s) failed: 2 on hgt, 1 on ecl`.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} document(s) failed",
+            self.documents - self.valid,
+            self.documents
+        )?;
+        let counts: Vec<String> = self
+            .by_count()
+            .iter()
+            .map(|(key, count)| format!("{} on {}", count, key))
+            .collect();
+        if !counts.is_empty() {
+            write!(f, ": {}", counts.join(", "))?;
         }
+        Ok(())
     }
+}
 
-    return true;
+/// Checks every document of a batch against `schema`.
+pub fn report<D: Document>(schema: &Schema, documents: &[D]) -> Report {
+    let mut report = Report::default();
+    for document in documents {
+        let validation = schema.validate(document);
+        report.documents += 1;
+        if validation.is_valid() {
+            report.valid += 1;
+        }
+        for violation in validation.violations {
+            *report.failures.entry(violation.key).or_insert(0) += 1;
+        }
+    }
+
+    report
+}
+
+/// Every field of `passport` breaking the puzzle rules.
+pub fn validate_passport(passport: &Passport) -> Validation {
+    DEFAULT_SCHEMA.validate(passport)
 }
 
-fn parse_passport(input: &str) -> Result<Passport, Box<dyn Error>> {
-    lazy_static! {
-        static ref MATCHER_BIRTH_YR: Regex = Regex::new(r"byr:(?P<birth_year>\d+)").unwrap();
-        static ref MATCHER_ISSUE_YR: Regex = Regex::new(r"iyr:(?P<issue_year>\d+)").unwrap();
-        static ref MATCHER_EXP_YR: Regex = Regex::new(r"eyr:(?P<expiration_year>\d+)").unwrap();
-        static ref MATCHER_HGHT: Regex = Regex::new(r"hgt:(?P<height>\d+[cm|in]*)").unwrap();
-        static ref MATCHER_HCL: Regex = Regex::new(r"hcl:(?P<hair_color>\S+)").unwrap();
-        static ref MATCHER_ECL: Regex = Regex::new(r"ecl:(?P<eye_color>\S+)").unwrap();
-        static ref MATCHER_PID: Regex = Regex::new(r"pid:(?P<passport_id>\S+)").unwrap();
-        static ref MATCHER_CID: Regex = Regex::new(r"cid:(?P<country_id>\S+)").unwrap();
+/// The puzzle rules: every field but `cid` is required, years have four
+/// digits within a range, heights are in cm or in, hair colors are hex and
+/// passport ids have nine digits.
+impl Default for Schema {
+    fn default() -> Self {
+        let year = |key: &str, min, max| Field {
+            key: key.to_string(),
+            required: true,
+            rule: Rule::Number {
+                digits: Some(4),
+                min: Some(min),
+                max: Some(max),
+            },
+        };
+        let field = |key: &str, required, rule| Field {
+            key: key.to_string(),
+            required,
+            rule,
+        };
+        let unit = |unit: &str, min, max| Unit {
+            unit: unit.to_string(),
+            min,
+            max,
+        };
+
+        Schema {
+            fields: vec![
+                year("byr", 1920, 2002),
+                year("iyr", 2010, 2020),
+                year("eyr", 2020, 2030),
+                field(
+                    "hgt",
+                    true,
+                    Rule::Measure {
+                        units: vec![unit("cm", 150, 193), unit("in", 59, 76)],
+                    },
+                ),
+                field(
+                    "hcl",
+                    true,
+                    Rule::Pattern {
+                        pattern: Regex::new(r"^#[0-9a-f]{6}$").unwrap(),
+                    },
+                ),
+                field(
+                    "ecl",
+                    true,
+                    Rule::OneOf {
+                        values: EyeColor::ALL.iter().map(|c| c.to_string()).collect(),
+                    },
+                ),
+                field(
+                    "pid",
+                    true,
+                    Rule::Pattern {
+                        pattern: Regex::new(r"^\d{9}$").unwrap(),
+                    },
+                ),
+                field("cid", false, Rule::Any),
+            ],
+        }
     }
+}
 
-    let birth_year = match MATCHER_BIRTH_YR.captures(input) {
-        Some(caps) => Some(caps.name("birth_year").unwrap().to_string()),
-        None => None,
-    };
-    let issue_year = match MATCHER_ISSUE_YR.captures(input) {
-        Some(caps) => Some(caps.name("issue_year").unwrap().to_string()),
-        None => None,
-    };
-    let expiration_year = match MATCHER_EXP_YR.captures(input) {
-        Some(caps) => Some(caps.name("expiration_year").unwrap().to_string()),
-        None => None,
-    };
-    let height = match MATCHER_HGHT.captures(input) {
-        Some(caps) => Some(caps.name("height").unwrap().to_string()),
-        None => None,
-    };
-    let hair_color = match MATCHER_HCL.captures(input) {
-        Some(caps) => Some(caps.name("hair_color").unwrap().to_string()),
-        None => None,
-    };
-    let eye_color = match MATCHER_ECL.captures(input) {
-        Some(caps) => Some(caps.name("eye_color").unwrap().to_string()),
-        None => None,
-    };
-    let passport_id = match MATCHER_PID.captures(input) {
-        Some(caps) => Some(caps.name("passport_id").unwrap().to_string()),
-        None => None,
-    };
-    let country_id = match MATCHER_CID.captures(input) {
-        Some(caps) => Some(caps.name("country_id").unwrap().parse()?),
-        None => None,
-    };
+/// Parses the blank line separated passport batch, skipping malformed
+/// tokens.
+#[aoc_generator(day4)]
+pub fn parse_input(input: &str) -> Result<Vec<Passport>, AocError> {
+    parse_passports(input, Mode::Lenient)
+}
 
-    let passport = Passport {
-        birth_year,
-        issue_year,
-        expiration_year,
-        height,
-        hair_color,
-        eye_color,
-        passport_id,
-        country_id,
-    };
+/// Parses the passport batch, failing on any malformed token, repeated or
+/// unknown key with `Mode::Strict`.
+pub fn parse_passports(input: &str, mode: Mode) -> Result<Vec<Passport>, AocError> {
+    let tokenized = tokenize(input, &DEFAULT_SCHEMA, mode)?;
+    Ok(tokenized.records.iter().map(Passport::from).collect())
+}
+
+/// Counts the passports that have every required field.
+#[aoc(day4, part1)]
+pub fn part_1(passport_list: &[Passport]) -> usize {
+    passport_list.iter().filter(|p| valid_passport(p)).count()
+}
+
+/// Counts the passports whose required fields are also valid.
+#[aoc(day4, part2)]
+pub fn part_2(passport_list: &[Passport]) -> usize {
+    passport_list
+        .iter()
+        .filter(|p| valid_passport_p2(p))
+        .count()
+}
+
+fn valid_passport(passport: &Passport) -> bool {
+    DEFAULT_SCHEMA.has_required(passport)
+}
 
-    Ok(passport)
+fn valid_passport_p2(passport: &Passport) -> bool {
+    DEFAULT_SCHEMA.is_valid(passport)
+}
+
+impl From<&Record> for Passport {
+    fn from(record: &Record) -> Self {
+        let mut passport = Passport::default();
+        let mut line = None;
+        for token in &record.tokens {
+            if passport.push(&token.key, token.value.clone(), line != Some(token.line)) {
+                line = Some(token.line);
+            }
+        }
+        passport
+    }
 }
 
-fn separate_input(input: &str) -> Result<Vec<String>, Box<dyn Error>> {
-    let mut separated: Vec<String> = Default::default();
-    let mut lines = input.lines();
-    let mut acc_lines: Vec<&str> = Default::default();
+/// Lines of every blank line separated record, along with their 0-based
+/// index in the input.
+fn separate_input(input: &str) -> Result<Vec<Vec<(usize, &str)>>, AocError> {
+    let mut separated: Vec<Vec<(usize, &str)>> = Default::default();
+    let mut lines = input.lines().enumerate();
+    let mut acc_lines: Vec<(usize, &str)> = Default::default();
     let mut appended = false;
     loop {
         match lines.next() {
-            Some(l) => {
+            Some((idx, l)) => {
                 if l.trim().is_empty() {
                     //Empty line
                     if !appended {
-                        separated.push(acc_lines.join(" "));
+                        separated.push(std::mem::take(&mut acc_lines));
                         appended = true;
-                        acc_lines.clear();
                     }
                 } else {
-                    acc_lines.push(l);
+                    acc_lines.push((idx, l));
                     appended = false;
                 }
             }
             None => {
-                separated.push(acc_lines.join(" "));
+                separated.push(acc_lines);
                 break;
             }
         }
@@ -252,12 +969,190 @@ fn separate_input(input: &str) -> Result<Vec<String>, Box<dyn Error>> {
     return Ok(separated);
 }
 
+/// Keys of the passport fields. CSV exports end with the columns of the keys
+/// no passport has, in this order.
+pub const KEYS: [&str; 8] = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"];
+
+/// Formats passport batches can be imported from and exported to. Fields
+/// keep the order they were read in, as far as the format allows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Records separated by blank lines, with the fields on the lines they
+    /// were read from, exactly like the puzzle input.
+    Puzzle,
+    /// A header with every key followed by a record per passport, missing
+    /// fields being empty. Columns follow the order keys first show up in.
+    Csv,
+    /// An array of objects keyed like the CSV header, without the missing
+    /// fields. Line breaks between fields are lost.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = AocError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "puzzle" => Ok(Format::Puzzle),
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(AocError::invalid_argument(format!(
+                "unknown format `{}`, expected puzzle, csv or json",
+                s
+            ))),
+        }
+    }
+}
+
+/// Writes `passports` in `format`. Passports without any field can't be
+/// told apart from the blank lines around them in the puzzle format.
+pub fn export(passports: &[Passport], format: Format) -> Result<String, AocError> {
+    let exported = match format {
+        Format::Puzzle => {
+            let mut records = vec![];
+            for (idx, passport) in passports.iter().enumerate() {
+                if passport.layout.is_empty() {
+                    return Err(export_error(format!("passport {} has no fields", idx + 1)));
+                }
+                let mut record = String::new();
+                for line in &passport.layout {
+                    let tokens: Vec<String> = line
+                        .iter()
+                        .map(|key| format!("{}:{}", key, passport.field(key).unwrap_or("")))
+                        .collect();
+                    record += &(tokens.join(" ") + "\n");
+                }
+                records.push(record);
+            }
+            records.join("\n")
+        }
+        Format::Csv => {
+            let mut header: Vec<&str> = vec![];
+            for key in passports
+                .iter()
+                .flat_map(Passport::keys)
+                .chain(KEYS.iter().copied())
+            {
+                if !header.contains(&key) {
+                    header.push(key);
+                }
+            }
+            let mut writer = csv::Writer::from_writer(vec![]);
+            writer.write_record(&header).map_err(export_error)?;
+            for passport in passports {
+                let row = header.iter().map(|key| passport.field(key).unwrap_or(""));
+                writer.write_record(row).map_err(export_error)?;
+            }
+            let bytes = writer.into_inner().map_err(export_error)?;
+            String::from_utf8(bytes).map_err(export_error)?
+        }
+        Format::Json => serde_json::to_string_pretty(passports).map_err(export_error)?,
+    };
+
+    Ok(exported)
+}
+
+fn export_error(e: impl fmt::Display) -> AocError {
+    AocError::invalid_argument(format!("exporting passports: {}", e))
+}
+
+/// Reads passports written in `format`, rejecting values the puzzle format
+/// couldn't hold.
+pub fn import(text: &str, format: Format) -> Result<Vec<Passport>, AocError> {
+    let passports: Vec<Passport> = match format {
+        Format::Puzzle => return parse_passports(text, Mode::Strict),
+        Format::Csv => import_csv(text)
+            .map_err(|e| AocError::invalid_argument(format!("passports csv: {}", e)))?,
+        Format::Json => serde_json::from_str(text)
+            .map_err(|e| AocError::invalid_argument(format!("passports json: {}", e)))?,
+    };
+    for (idx, passport) in passports.iter().enumerate() {
+        for key in &KEYS {
+            match passport.field(key) {
+                Some(value) if value.is_empty() || value.contains(char::is_whitespace) => {
+                    return Err(AocError::invalid_argument(format!(
+                        "passport {}: {}: expected a value without spaces, found {:?}",
+                        idx + 1,
+                        key,
+                        value
+                    )));
+                }
+                _ => (),
+            }
+        }
+    }
+
+    Ok(passports)
+}
+
+/// Reads CSV rows into passports with their fields in column order.
+fn import_csv(text: &str) -> Result<Vec<Passport>, String> {
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let header = reader.headers().map_err(|e| e.to_string())?.clone();
+    for (idx, key) in header.iter().enumerate() {
+        if !KEYS.contains(&key) {
+            return Err(format!("unknown column `{}`", key));
+        }
+        if header.iter().take(idx).any(|k| k == key) {
+            return Err(format!("duplicate column `{}`", key));
+        }
+    }
+    let mut passports = vec![];
+    for row in reader.records() {
+        let row = row.map_err(|e| e.to_string())?;
+        let mut passport = Passport::default();
+        for (key, value) in header.iter().zip(&row) {
+            if !value.is_empty() {
+                passport.push(key, value.to_string(), false);
+            }
+        }
+        passports.push(passport);
+    }
+    Ok(passports)
+}
+
+/// Solver for day 4, usable outside of cargo-aoc.
+pub struct Day04;
+
+impl Solver for Day04 {
+    type Input = Vec<Passport>;
+    type Output = usize;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        parse_input(input)
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(part_1(input))
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(part_2(input))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+    use std::fs;
 
     const  EXAMPLE_INPUT : &str = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\nbyr:1937 iyr:2017 cid:147 hgt:183cm\n\niyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884\nhcl:#cfa07d byr:1929\n\nhcl:#ae17e1 iyr:2013\neyr:2024\necl:brn pid:760753108 byr:1931\nhgt:179cm\n\nhcl:#cfa07d eyr:2025 pid:166559648\niyr:2011 ecl:brn hgt:59in";
 
+    const INVALID: &str = "eyr:1972 cid:100
+hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926
+
+iyr:2019
+hcl:#602927 eyr:1967 hgt:170cm
+ecl:grn pid:012533040 byr:1946
+
+hcl:dab227 iyr:2012
+ecl:brn hgt:182cm pid:021572410 eyr:2020 byr:1992 cid:277
+
+hgt:59cm ecl:zzz
+eyr:2038 hcl:74454a iyr:2023
+pid:3556412378 byr:2007";
+
     #[test]
     fn test_parse_input() {
         let output = parse_input(EXAMPLE_INPUT).unwrap();
@@ -286,11 +1181,9 @@ mod tests {
 
     #[test]
     fn test_parse_passport() {
-        let inputs = separate_input(EXAMPLE_INPUT).unwrap();
-        let passport = parse_passport(&inputs[0]).unwrap();
-        let passport2 = parse_passport(&inputs[1]).unwrap();
-        let passport3 = parse_passport(&inputs[2]).unwrap();
-        let passport4 = parse_passport(&inputs[3]).unwrap();
+        let passports = parse_passports(EXAMPLE_INPUT, Mode::Strict).unwrap();
+        let (passport, passport2, passport3, passport4) =
+            (&passports[0], &passports[1], &passports[2], &passports[3]);
         assert_eq!(passport.birth_year, Some("1937".to_string()));
         assert_eq!(passport2.birth_year, Some("1929".to_string()));
         assert_eq!(passport3.birth_year, Some("1931".to_string()));
@@ -339,19 +1232,6 @@ mod tests {
 
     #[test]
     fn test_invalid_p2() {
-        const INVALID: &str = "eyr:1972 cid:100
-hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926
-
-iyr:2019
-hcl:#602927 eyr:1967 hgt:170cm
-ecl:grn pid:012533040 byr:1946
-
-hcl:dab227 iyr:2012
-ecl:brn hgt:182cm pid:021572410 eyr:2020 byr:1992 cid:277
-
-hgt:59cm ecl:zzz
-eyr:2038 hcl:74454a iyr:2023
-pid:3556412378 byr:2007";
         let passports = parse_input(INVALID).unwrap();
         assert_eq!(valid_passport_p2(&passports[0]), false);
         assert_eq!(valid_passport_p2(&passports[1]), false);
@@ -381,13 +1261,339 @@ iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719";
         assert_eq!(valid_passport_p2(&passports[3]), true);
     }
 
+    #[test]
+    fn test_rules() {
+        let schema = Schema::default();
+        let rule = |key: &str| &schema.fields.iter().find(|f| f.key == key).unwrap().rule;
+        assert_eq!(rule("byr").check("2002"), Ok(()));
+        assert!(rule("byr").check("2003").is_err());
+        assert!(rule("byr").check("02002").is_err());
+        assert_eq!(rule("hgt").check("60in"), Ok(()));
+        assert_eq!(rule("hgt").check("190cm"), Ok(()));
+        assert!(rule("hgt").check("190in").is_err());
+        assert!(rule("hgt").check("190").is_err());
+        assert!(rule("hgt").check("190cmin").is_err());
+        assert_eq!(rule("hcl").check("#123abc"), Ok(()));
+        assert!(rule("hcl").check("#123abz").is_err());
+        assert!(rule("hcl").check("123abc").is_err());
+        assert_eq!(rule("ecl").check("brn"), Ok(()));
+        assert!(rule("ecl").check("wat").is_err());
+        assert_eq!(rule("pid").check("000000001"), Ok(()));
+        assert!(rule("pid").check("0123456789").is_err());
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema = Schema::default();
+        assert_eq!(
+            Schema::from_toml(&schema.to_toml().unwrap()),
+            Ok(schema.clone())
+        );
+        assert_eq!(Schema::from_json(&schema.to_json().unwrap()), Ok(schema));
+
+        const LIBRARY_CARD: &str = r#"
+            [[fields]]
+            key = "num"
+            required = true
+            type = "number"
+            digits = 6
+
+            [[fields]]
+            key = "exp"
+            type = "pattern"
+            pattern = '^\d{4}-\d{2}$'
+        "#;
+        let schema = Schema::from_toml(LIBRARY_CARD).unwrap();
+        let cards = tokenize(
+            "num:012345 exp:2024-05\n\nexp:2024-05\n\nnum:012345 exp:may",
+            &schema,
+            Mode::Strict,
+        )
+        .unwrap()
+        .records;
+        let valid: Vec<bool> = cards.iter().map(|c| schema.is_valid(c)).collect();
+        assert_eq!(valid, vec![true, false, false]);
+        assert!(schema.has_required(&cards[2]));
+
+        assert!(Schema::from_toml("[[fields]]\nkey = \"a\"\ntype = \"colour\"").is_err());
+        assert!(Schema::from_json(
+            r#"{"fields": [{"key": "a", "type": "pattern", "pattern": "("}]}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let passports = parse_input(INVALID).unwrap();
+        let validation = validate_passport(&passports[0]);
+        let violations: Vec<String> = validation
+            .violations
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(
+            violations,
+            vec![
+                "eyr: `1972`: expected at least 2020",
+                "hgt: `170`: expected a unit among cm, in",
+                "pid: `186cm`: expected to match `^\\d{9}$`",
+            ]
+        );
+        assert!(!validation.is_valid());
+
+        let mut missing = validate_passport(&parse_input("byr:1937 hgt:183cm").unwrap()[0]);
+        assert_eq!(missing.violations.len(), 5);
+        assert_eq!(missing.violations.remove(0).to_string(), "iyr: missing");
+
+        let valid =
+            parse_input("pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f")
+                .unwrap();
+        assert_eq!(validate_passport(&valid[0]), Validation::default());
+    }
+
+    #[test]
+    fn test_report() {
+        let invalid = report(&Schema::default(), &parse_input(INVALID).unwrap());
+        assert_eq!(invalid.valid, 0);
+        assert_eq!(invalid.failures["eyr"], 3);
+        assert_eq!(
+            invalid.to_string(),
+            "4 of 4 document(s) failed: 3 on eyr, 2 on hcl, 2 on hgt, 2 on pid, 1 on byr, 1 on ecl, 1 on iyr"
+        );
+
+        let example = report(&Schema::default(), &parse_input(EXAMPLE_INPUT).unwrap());
+        assert_eq!(
+            example.to_string(),
+            "2 of 4 document(s) failed: 1 on byr, 1 on hgt"
+        );
+    }
+
+    #[test]
+    fn test_tokenize() {
+        const MESSY: &str = "byr:1990 hgt:190cmin\nfoo byr:2000\n\n\nxyz:1 pid: :1 pid:012345678";
+        let schema = Schema::default();
+        let tokenized = tokenize(MESSY, &schema, Mode::Lenient).unwrap();
+        assert_eq!(tokenized.records.len(), 2);
+        assert_eq!(tokenized.records[0].field("byr"), Some("1990"));
+        assert_eq!(tokenized.records[0].field("hgt"), Some("190cmin"));
+        let keys: Vec<&str> = tokenized.records[1]
+            .tokens
+            .iter()
+            .map(|t| t.key.as_str())
+            .collect();
+        assert_eq!(keys, vec!["xyz", "pid"]);
+        assert_eq!(
+            tokenized.records[1]
+                .token("pid")
+                .map(|t| (t.line, t.column)),
+            Some((5, 15))
+        );
+
+        let issues: Vec<String> = tokenized.issues.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            issues,
+            vec![
+                "day 4, line 2, column 1: expected `key:value`: \"foo\"",
+                "day 4, line 2, column 5: duplicate key, first at line 1, column 1: \"byr:2000\"",
+                "day 4, line 5, column 1: unknown key: \"xyz:1\"",
+                "day 4, line 5, column 7: expected `key:value`: \"pid:\"",
+                "day 4, line 5, column 12: expected `key:value`: \":1\"",
+            ]
+        );
+        assert_eq!(
+            tokenize(MESSY, &schema, Mode::Strict),
+            Err(tokenized.issues[0].clone())
+        );
+        assert!(parse_passports(MESSY, Mode::Strict).is_err());
+        assert_eq!(parse_input(MESSY).unwrap().len(), 2);
+        assert_eq!("strict".parse(), Ok(Mode::Strict));
+        assert!("loose".parse::<Mode>().is_err());
+    }
+
+    #[test]
+    fn test_tokenize_empty_groups() {
+        assert_eq!(parse_input("\nbyr:1937 iyr:2017\n\n").unwrap().len(), 1);
+        assert!(parse_input("").unwrap().is_empty());
+        assert!(parse_input(" \n\n\t").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_export() {
+        // The puzzle format reads back to the same text
+        let text = format!("{}\n", EXAMPLE_INPUT);
+        let passports = import(&text, Format::Puzzle).unwrap();
+        assert_eq!(export(&passports, Format::Puzzle).unwrap(), text);
+        let puzzle = export(&passports[1..3], Format::Puzzle).unwrap();
+        assert_eq!(
+            puzzle,
+            "iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884\nhcl:#cfa07d byr:1929\n\nhcl:#ae17e1 iyr:2013\neyr:2024\necl:brn pid:760753108 byr:1931\nhgt:179cm\n"
+        );
+        let csv = export(&passports[1..3], Format::Csv).unwrap();
+        assert_eq!(
+            csv,
+            "iyr,ecl,cid,eyr,pid,hcl,byr,hgt\n2013,amb,350,2023,028048884,#cfa07d,1929,\n2013,brn,,2024,760753108,#ae17e1,1931,179cm\n"
+        );
+        let json = export(&passports[3..], Format::Json).unwrap();
+        assert!(json.find("\"eyr\"") < json.find("\"iyr\""));
+        assert!(!json.contains("byr"));
+        let json = import(r#"[{"pid": "028048884", "byr": "1929"}]"#, Format::Json).unwrap();
+        assert_eq!(
+            export(&json, Format::Puzzle).unwrap(),
+            "pid:028048884 byr:1929\n"
+        );
+        assert_eq!(import("", Format::Puzzle).unwrap(), vec![]);
+
+        // Columns may come in any order
+        let reordered = import("pid,byr\n028048884,1929\n,\n", Format::Csv).unwrap();
+        assert_eq!(reordered[0].field("pid"), Some("028048884"));
+        assert_eq!(reordered[1], Passport::default());
+        assert_eq!(
+            export(&reordered[..1], Format::Puzzle).unwrap(),
+            "pid:028048884 byr:1929\n"
+        );
+        assert!(export(&reordered, Format::Puzzle).is_err());
+        assert!(import("pid,dob\n1,2\n", Format::Csv).is_err());
+        assert!(import("pid,pid\n1,2\n", Format::Csv).is_err());
+
+        assert!(import(r#"[{"byr": "19 29"}]"#, Format::Json).is_err());
+        assert!(import(r#"[{"dob": "1929"}]"#, Format::Json).is_err());
+        assert!(import("byr:1929 byr:1930", Format::Puzzle).is_err());
+        assert_eq!("csv".parse(), Ok(Format::Csv));
+    }
+
+    fn passports() -> impl Strategy<Value = Vec<Passport>> {
+        let value = proptest::option::of("[!-~]{1,8}");
+        let fields = (
+            Just(KEYS.to_vec()).prop_shuffle(),
+            proptest::collection::vec(value, 8),
+            proptest::collection::vec(any::<bool>(), 8),
+        );
+        let passport = fields
+            .prop_filter("passports need a field", |(_, f, _)| {
+                f.iter().any(Option::is_some)
+            })
+            .prop_map(|(keys, values, breaks)| {
+                let mut passport = Passport::default();
+                for ((key, value), new_line) in keys.iter().zip(values).zip(breaks) {
+                    if let Some(value) = value {
+                        passport.push(key, value, new_line);
+                    }
+                }
+                passport
+            });
+        proptest::collection::vec(passport, 0..10)
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(passports in passports()) {
+            for format in [Format::Puzzle, Format::Csv, Format::Json] {
+                let exported = export(&passports, format).unwrap();
+                let imported = import(&exported, format).unwrap();
+                prop_assert_eq!(&export(&imported, format).unwrap(), &exported);
+                prop_assert_eq!(imported.len(), passports.len());
+                for (imported, passport) in imported.iter().zip(&passports) {
+                    for key in &KEYS {
+                        prop_assert_eq!(imported.field(key), passport.field(key));
+                    }
+                }
+                if format == Format::Puzzle {
+                    prop_assert_eq!(&imported, &passports);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_input() {
+        let text = fs::read_to_string(crate::input_path(DAY)).unwrap();
+        let passports = import(&text, Format::Puzzle).unwrap();
+        assert_eq!(export(&passports, Format::Puzzle).unwrap(), text);
+        for format in [Format::Csv, Format::Json] {
+            let exported = export(&passports, format).unwrap();
+            assert_eq!(import(&exported, format).unwrap().len(), passports.len());
+        }
+    }
+
     #[test]
     fn test_parse_height() {
-        let h = parse_height("186cm");
-        assert_eq!(h, Height::CM(186));
-        let h2 = parse_height("74in");
-        assert_eq!(h2, Height::IN(74));
-        let h3 = parse_height("74");
-        assert_eq!(h3, Height::NONE);
+        let h: Height = "186cm".parse().unwrap();
+        assert_eq!(h, Height::new(186, LengthUnit::Cm));
+        let h2: Height = "74in".parse().unwrap();
+        assert_eq!(h2, Height::new(74, LengthUnit::In));
+        assert!("74".parse::<Height>().is_err());
+        assert!("cm".parse::<Height>().is_err());
+        assert!("190cmin".parse::<Height>().is_err());
+        assert!("99999999999999999999mm".parse::<Height>().is_err());
+        assert_eq!(h2.to_string(), "74in");
+    }
+
+    #[test]
+    fn test_height_units() {
+        let inches = Height::new(74, LengthUnit::In);
+        assert!((inches.in_unit(LengthUnit::Cm) - 187.96).abs() < 1e-9);
+        assert_eq!(inches.to(LengthUnit::Cm), Height::new(188, LengthUnit::Cm));
+        assert_eq!(inches.to(LengthUnit::Mm), Height::new(1880, LengthUnit::Mm));
+        assert_eq!(inches.to(LengthUnit::Ft), Height::new(6, LengthUnit::Ft));
+        let feet: Height = "6ft".parse().unwrap();
+        assert_eq!(feet.to(LengthUnit::In), Height::new(72, LengthUnit::In));
+        assert_eq!(feet.to(LengthUnit::Cm).to_string(), "183cm");
+
+        // Heights past any passport convert without overflowing
+        let highest = Height::new(u64::MAX, LengthUnit::Mm);
+        assert_eq!(highest.to(LengthUnit::Mm), highest);
+        assert_eq!(highest.to(LengthUnit::Cm).value, u64::MAX / 10 + 1);
+        assert_eq!(
+            Height::new(u64::MAX, LengthUnit::Ft).to(LengthUnit::Mm),
+            highest
+        );
+        assert_eq!(
+            Height::new(u64::MAX / 304_800, LengthUnit::Ft)
+                .to(LengthUnit::In)
+                .value,
+            u64::MAX / 304_800 * 12
+        );
+        assert_eq!(highest.in_unit(LengthUnit::Mm), u64::MAX as f64);
+    }
+
+    #[test]
+    fn test_typed_passport() {
+        let passports = parse_input(EXAMPLE_INPUT).unwrap();
+        let typed = TypedPassport::try_from(&passports[0]).unwrap();
+        assert_eq!(typed.birth_year, 1937);
+        assert_eq!(typed.height, Height::new(183, LengthUnit::Cm));
+        assert_eq!(
+            typed.hair_color,
+            Color {
+                red: 0xff,
+                green: 0xff,
+                blue: 0xfd
+            }
+        );
+        assert_eq!(typed.eye_color, EyeColor::Gray);
+        assert_eq!(typed.passport_id, "860033327");
+        assert_eq!(
+            typed.to_string(),
+            "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327 cid:147"
+        );
+        let reparsed = parse_input(&typed.to_string()).unwrap();
+        assert_eq!(TypedPassport::try_from(&reparsed[0]), Ok(typed));
+
+        let missing = TypedPassport::try_from(&passports[1]).unwrap_err();
+        assert_eq!(missing.to_string(), "hgt: missing");
+        let invalid = parse_input(INVALID).unwrap();
+        let wrong = TypedPassport::try_from(&invalid[0]).unwrap_err();
+        assert_eq!(
+            wrong.to_string(),
+            "hgt: `170`: expected a height like `183cm`, found `170`"
+        );
+        let wrong = TypedPassport::try_from(&invalid[3]).unwrap_err();
+        assert_eq!(wrong.key, "hcl");
+
+        assert_eq!("#A97842".parse::<Color>().unwrap().to_string(), "#a97842");
+        assert!("#a9784".parse::<Color>().is_err());
+        assert!("#+f0000".parse::<Color>().is_err());
+        assert_eq!("hzl".parse(), Ok(EyeColor::Hazel));
+        assert!("zzz".parse::<EyeColor>().is_err());
     }
 }
@@ -1,68 +1,262 @@
+use crate::stream::records;
+use crate::{AocError, Solver, StreamSolver};
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::io::BufRead;
+
+const DAY: u32 = 5;
 
 type Seat = (u64, u64, u64);
 
+/// Decodes every boarding pass into `(row, column, id)`.
 #[aoc_generator(day5)]
-fn parse_input_day5(input: &str) -> Vec<Seat> {
-    input.lines().map(|l| parse_seat(l)).collect()
+pub fn parse_input_day5(input: &str) -> Result<Vec<Seat>, AocError> {
+    read_seats(input.as_bytes()).collect()
+}
+
+/// Decodes the boarding passes one line at a time out of `reader`.
+pub fn read_seats<R: BufRead>(reader: R) -> impl Iterator<Item = Result<Seat, AocError>> {
+    records(reader, |idx, l| {
+        parse_seat(l).map_err(|e| e.offset_lines(idx))
+    })
 }
 
+/// Highest seat id on a boarding pass.
 #[aoc(day5, part1)]
-fn day5_part1(input: &[Seat]) -> u64 {
-    input.iter().max_by(|x, y| x.2.cmp(&y.2)).unwrap().2
+pub fn day5_part1(input: &[Seat]) -> Result<u64, AocError> {
+    match input.iter().max_by(|x, y| x.2.cmp(&y.2)) {
+        Some(seat) => Ok(seat.2),
+        None => Err(AocError::no_solution(DAY, "there are no boarding passes")),
+    }
 }
 
+/// Id of the only free seat between two taken ones.
 #[aoc(day5, part2)]
-fn day5_part2(input: &[Seat]) -> u64 {
+pub fn day5_part2(input: &[Seat]) -> Result<u64, AocError> {
     let mut sorted = input.to_vec();
     sorted.sort_by(|x, y| x.cmp(y));
     let len = sorted.len();
 
-    for i in 0..(len - 1) {
+    for i in 0..len.saturating_sub(1) {
         let current_seat = sorted[i];
         let next_seat = sorted[i + 1];
 
         if next_seat.2 > current_seat.2 + 1 {
-            return current_seat.2 + 1;
+            return Ok(current_seat.2 + 1);
         }
     }
-    unreachable!();
+    Err(AocError::no_solution(
+        DAY,
+        "there is no free seat between two taken ones",
+    ))
 }
 
-fn parse_seat(seat: &str) -> Seat {
-    let row = &seat[..7];
-    let row_transformed: String = row
-        .chars()
-        .map(|c| match c {
-            'F' => '0',
-            'B' => '1',
-            _ => unreachable!(),
-        })
-        .collect();
-    let column = &seat[7..];
-    let column_transformed: String = column
-        .chars()
-        .map(|c| match c {
-            'L' => '0',
-            'R' => '1',
-            _ => unreachable!(),
+/// Boarding pass layout of a plane with `rows` rows of `columns` seats each.
+/// Passes are the row then the column in binary, written with a letter for
+/// the lower and one for the upper half of each.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeatCodec {
+    rows: u64,
+    columns: u64,
+    row_letters: (char, char),
+    column_letters: (char, char),
+}
+
+/// The puzzle plane: 128 rows written with `F` and `B`, of 8 seats written
+/// with `L` and `R`.
+impl Default for SeatCodec {
+    fn default() -> Self {
+        SeatCodec {
+            rows: 128,
+            columns: 8,
+            row_letters: ('F', 'B'),
+            column_letters: ('L', 'R'),
+        }
+    }
+}
+
+impl SeatCodec {
+    /// Letters are given as `(lower, upper)`. Planes may have up to 2^31 rows
+    /// and as many columns, so every id fits.
+    pub fn new(
+        rows: u64,
+        columns: u64,
+        row_letters: (char, char),
+        column_letters: (char, char),
+    ) -> Result<SeatCodec, AocError> {
+        let limit = 1 << 31;
+        if !(1..=limit).contains(&rows) || !(1..=limit).contains(&columns) {
+            return Err(AocError::invalid_argument(format!(
+                "expected between 1 and {} rows and columns, found {}x{}",
+                limit, rows, columns
+            )));
+        }
+        if row_letters.0 == row_letters.1 || column_letters.0 == column_letters.1 {
+            return Err(AocError::invalid_argument(
+                "the lower and upper half need different letters",
+            ));
+        }
+
+        Ok(SeatCodec {
+            rows,
+            columns,
+            row_letters,
+            column_letters,
         })
-        .collect();
+    }
 
-    let row_u64 = parse_bin(&row_transformed);
-    let col_u64 = parse_bin(&column_transformed);
+    fn row_len(&self) -> usize {
+        bits(self.rows)
+    }
+
+    fn column_len(&self) -> usize {
+        bits(self.columns)
+    }
+
+    /// Characters in a boarding pass.
+    pub fn pass_len(&self) -> usize {
+        self.row_len() + self.column_len()
+    }
+
+    pub fn id(&self, row: u64, column: u64) -> u64 {
+        row * self.columns + column
+    }
 
-    (row_u64, col_u64, row_u64 * 8 + col_u64)
+    /// Decodes a boarding pass into `(row, column, id)`, reporting errors as
+    /// if it was the first line.
+    pub fn decode(&self, pass: &str) -> Result<Seat, AocError> {
+        let chars: Vec<char> = pass.chars().collect();
+        if chars.len() != self.pass_len() {
+            return Err(AocError::parse(
+                DAY,
+                1,
+                1,
+                pass,
+                format!("expected {} characters", self.pass_len()),
+            ));
+        }
+        let decode = |offset: usize, len: usize, (zero, one): (char, char), count: u64| {
+            let mut value = 0;
+            for (i, c) in chars[offset..offset + len].iter().enumerate() {
+                let bit = match *c {
+                    c if c == zero => 0,
+                    c if c == one => 1,
+                    c => {
+                        return Err(AocError::parse(
+                            DAY,
+                            1,
+                            offset + i + 1,
+                            &c.to_string(),
+                            format!("expected `{}` or `{}`", zero, one),
+                        ))
+                    }
+                };
+                value = value << 1 | bit;
+            }
+            if value >= count {
+                let text: String = chars[offset..offset + len].iter().collect();
+                return Err(AocError::parse(
+                    DAY,
+                    1,
+                    offset + 1,
+                    &text,
+                    format!("expected less than {}, found {}", count, value),
+                ));
+            }
+            Ok(value)
+        };
+        let row = decode(0, self.row_len(), self.row_letters, self.rows)?;
+        let column = decode(
+            self.row_len(),
+            self.column_len(),
+            self.column_letters,
+            self.columns,
+        )?;
+
+        Ok((row, column, self.id(row, column)))
+    }
+
+    /// Boarding pass of the seat at `row` and `column`.
+    pub fn encode(&self, row: u64, column: u64) -> Result<String, AocError> {
+        if row >= self.rows || column >= self.columns {
+            return Err(AocError::invalid_argument(format!(
+                "seat {},{} is outside of a plane of {} rows of {} seats",
+                row, column, self.rows, self.columns
+            )));
+        }
+        let letters = |value: u64, len: usize, (zero, one): (char, char)| {
+            (0..len)
+                .rev()
+                .map(move |bit| if value >> bit & 1 == 1 { one } else { zero })
+        };
+
+        Ok(letters(row, self.row_len(), self.row_letters)
+            .chain(letters(column, self.column_len(), self.column_letters))
+            .collect())
+    }
 }
 
-fn parse_bin(b: &str) -> u64 {
-    u64::from_str_radix(b, 2).unwrap()
+/// Letters needed to write any of `count` values in binary.
+fn bits(count: u64) -> usize {
+    count.next_power_of_two().trailing_zeros() as usize
+}
+
+/// Decodes a single boarding pass of the puzzle plane, reporting errors as
+/// if it was the first line.
+fn parse_seat(seat: &str) -> Result<Seat, AocError> {
+    SeatCodec::default().decode(seat)
+}
+
+/// Solver for day 5, usable outside of cargo-aoc.
+pub struct Day05;
+
+impl Solver for Day05 {
+    type Input = Vec<Seat>;
+    type Output = u64;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        parse_input_day5(input)
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        day5_part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        day5_part2(input)
+    }
+}
+
+impl StreamSolver for Day05 {
+    fn part1_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        let mut highest = None;
+        for seat in read_seats(reader) {
+            highest = highest.max(Some(seat?.2));
+        }
+        highest.ok_or_else(|| AocError::no_solution(DAY, "there are no boarding passes"))
+    }
+
+    fn part2_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        // Ids are 10 bits, so the taken seats fit in a fixed table
+        let mut taken = [false; 1024];
+        for seat in read_seats(reader) {
+            taken[seat?.2 as usize] = true;
+        }
+        let first = taken.iter().position(|t| *t).unwrap_or(taken.len());
+        let last = taken.iter().rposition(|t| *t).unwrap_or(0);
+        (first..last)
+            .find(|id| !taken[*id])
+            .map(|id| id as u64)
+            .ok_or_else(|| {
+                AocError::no_solution(DAY, "there is no free seat between two taken ones")
+            })
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use proptest::prelude::*;
 
     const EXAMPLE_INPUT: &str = "BFFFBBFRRR
 FFFBBBFRRR
@@ -70,21 +264,88 @@ BBFFBBFRLL";
     #[test]
     fn test_parse_input() {
         let seat_input: Vec<&str> = EXAMPLE_INPUT.lines().collect();
-        assert_eq!(parse_seat(seat_input[0]), (70, 7, 567));
-        assert_eq!(parse_seat(seat_input[1]), (14, 7, 119));
-        assert_eq!(parse_seat(seat_input[2]), (102, 4, 820));
+        assert_eq!(parse_seat(seat_input[0]).unwrap(), (70, 7, 567));
+        assert_eq!(parse_seat(seat_input[1]).unwrap(), (14, 7, 119));
+        assert_eq!(parse_seat(seat_input[2]).unwrap(), (102, 4, 820));
     }
 
     #[test]
     fn test_p1() {
-        let seats = parse_input_day5(EXAMPLE_INPUT);
-        let max_id = day5_part1(&seats);
+        let seats = parse_input_day5(EXAMPLE_INPUT).unwrap();
+        let max_id = day5_part1(&seats).unwrap();
         assert_eq!(max_id, 820);
     }
 
     #[test]
     fn test_p2() {
-        let seats = parse_input_day5(EXAMPLE_INPUT);
-        let id = day5_part2(&seats);
+        let seats = parse_input_day5(EXAMPLE_INPUT).unwrap();
+        // The first free id after a taken one
+        assert_eq!(day5_part2(&seats), Ok(120));
+        assert!(day5_part2(&seats[..1]).is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_input_day5("BFFFBBFRRR\nFFFBXBFRRR").err(),
+            Some(AocError::parse(5, 2, 5, "X", "expected `F` or `B`"))
+        );
+        assert_eq!(
+            parse_seat("BFFFBBFRRF").err(),
+            Some(AocError::parse(5, 1, 10, "F", "expected `L` or `R`"))
+        );
+        assert!(parse_seat("BFFFBBFRR").is_err());
+    }
+
+    #[test]
+    fn test_codec() {
+        let codec = SeatCodec::default();
+        assert_eq!(codec.encode(70, 7).unwrap(), "BFFFBBFRRR");
+        assert_eq!(codec.encode(102, 4).unwrap(), "BBFFBBFRLL");
+        assert!(codec.encode(128, 0).is_err());
+
+        // 100 rows take 7 letters and 6 columns 3, some passes being past the end
+        let codec = SeatCodec::new(100, 6, ('A', 'Z'), ('<', '>')).unwrap();
+        assert_eq!(codec.pass_len(), 10);
+        assert_eq!(codec.encode(99, 5).unwrap(), "ZZAAAZZ><>");
+        assert_eq!(codec.decode("ZZAAAZZ><>"), Ok((99, 5, 599)));
+        assert_eq!(
+            codec.decode("ZZAAZAAAAA").err(),
+            Some(AocError::parse(
+                5,
+                1,
+                1,
+                "ZZAAZAA",
+                "expected less than 100, found 100"
+            ))
+        );
+        assert_eq!(
+            codec.decode("AAAAAAA>>>").err(),
+            Some(AocError::parse(
+                5,
+                1,
+                8,
+                ">>>",
+                "expected less than 6, found 7"
+            ))
+        );
+
+        let single = SeatCodec::new(1, 2, ('F', 'B'), ('L', 'R')).unwrap();
+        assert_eq!(single.encode(0, 1).unwrap(), "R");
+        assert_eq!(single.decode("L"), Ok((0, 0, 0)));
+
+        assert!(SeatCodec::new(0, 8, ('F', 'B'), ('L', 'R')).is_err());
+        assert!(SeatCodec::new(128, 8, ('F', 'F'), ('L', 'R')).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_codec_round_trip(rows in 1..300u64, columns in 1..20u64, seat in (0..300u64, 0..20u64)) {
+            let (row, column) = (seat.0 % rows, seat.1 % columns);
+            let codec = SeatCodec::new(rows, columns, ('F', 'B'), ('L', 'R')).unwrap();
+            let pass = codec.encode(row, column).unwrap();
+            prop_assert_eq!(pass.len(), codec.pass_len());
+            prop_assert_eq!(codec.decode(&pass), Ok((row, column, row * columns + column)));
+        }
     }
 }
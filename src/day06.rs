@@ -1,3 +1,4 @@
+use crate::{AocError, Solver};
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::collections::hash_map::HashMap;
 use std::collections::hash_set::HashSet;
@@ -5,15 +6,30 @@ use std::mem;
 
 type AnswerGroup = Vec<String>;
 
+const DAY: u32 = 6;
+
+/// Groups the answers of every person by their group.
 #[aoc_generator(day6)]
-fn parse_input(input: &str) -> Vec<AnswerGroup> {
+pub fn parse_input(input: &str) -> Result<Vec<AnswerGroup>, AocError> {
     let mut answer_groups: Vec<AnswerGroup> = Default::default();
-    let mut lines = input.lines();
+    let mut lines = input.lines().enumerate();
     let mut acc_lines: Vec<String> = Default::default();
     let mut appended = false;
     loop {
         match lines.next() {
-            Some(l) => {
+            Some((idx, l)) => {
+                let mut chars = l.chars().enumerate();
+                if let Some((j, c)) =
+                    chars.find(|(_, c)| !c.is_ascii_lowercase() && !c.is_whitespace())
+                {
+                    return Err(AocError::parse(
+                        DAY,
+                        idx + 1,
+                        j + 1,
+                        &c.to_string(),
+                        "expected a question from `a` to `z`",
+                    ));
+                }
                 if l.trim().is_empty() {
                     //Empty line
                     if !appended {
@@ -32,19 +48,21 @@ fn parse_input(input: &str) -> Vec<AnswerGroup> {
             }
         }
     }
-    return answer_groups;
+    Ok(answer_groups)
 }
 
+/// Sum over groups of the questions anyone answered.
 #[aoc(day6, part1)]
-fn part_1(answer_group_list: &[AnswerGroup]) -> usize {
+pub fn part_1(answer_group_list: &[AnswerGroup]) -> usize {
     answer_group_list
         .iter()
         .map(|ag| count_answers(ag))
         .fold(0, |acc, ct| acc + ct)
 }
 
+/// Sum over groups of the questions everyone answered.
 #[aoc(day6, part2)]
-fn part_2(answer_group_list: &[AnswerGroup]) -> usize {
+pub fn part_2(answer_group_list: &[AnswerGroup]) -> usize {
     answer_group_list
         .iter()
         .map(|ag| count_answers2(ag))
@@ -83,6 +101,26 @@ fn count_answers2(answer_group: &AnswerGroup) -> usize {
     return count;
 }
 
+/// Solver for day 6, usable outside of cargo-aoc.
+pub struct Day06;
+
+impl Solver for Day06 {
+    type Input = Vec<AnswerGroup>;
+    type Output = usize;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        parse_input(input)
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(part_1(input))
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(part_2(input))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -91,14 +129,14 @@ mod test {
 
     #[test]
     fn test_separate_input() {
-        let answer_groups = parse_input(EXAMPLE_INPUT);
+        let answer_groups = parse_input(EXAMPLE_INPUT).unwrap();
         println!("{:?}", answer_groups);
         assert_eq!(answer_groups.len(), 5);
     }
 
     #[test]
     fn test_count_answers() {
-        let answer_groups = parse_input(EXAMPLE_INPUT);
+        let answer_groups = parse_input(EXAMPLE_INPUT).unwrap();
         assert_eq!(count_answers(&answer_groups[0]), 3);
         assert_eq!(count_answers(&answer_groups[1]), 3);
         assert_eq!(count_answers(&answer_groups[2]), 3);
@@ -108,19 +146,33 @@ mod test {
 
     #[test]
     fn test_example_p1() {
-        let answer_groups = parse_input(EXAMPLE_INPUT);
+        let answer_groups = parse_input(EXAMPLE_INPUT).unwrap();
         assert_eq!(part_1(&answer_groups), 11);
     }
 
     #[test]
     fn test_count2() {
-        let answer_groups = parse_input(EXAMPLE_INPUT);
+        let answer_groups = parse_input(EXAMPLE_INPUT).unwrap();
         assert_eq!(count_answers2(&answer_groups[0]), 3);
     }
 
     #[test]
     fn test_example_p2() {
-        let answer_groups = parse_input(EXAMPLE_INPUT);
+        let answer_groups = parse_input(EXAMPLE_INPUT).unwrap();
         assert_eq!(part_2(&answer_groups), 6);
     }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_input("abc\n\naB").err(),
+            Some(AocError::parse(
+                6,
+                3,
+                2,
+                "B",
+                "expected a question from `a` to `z`"
+            ))
+        );
+    }
 }
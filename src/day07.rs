@@ -1,35 +1,42 @@
+use crate::error::{column, parse_token};
+use crate::{AocError, Solver};
 use aoc_runner_derive::{aoc, aoc_generator};
 use lazy_static::lazy_static;
 use regex::Regex;
-use simple_error::bail;
 use std::collections::hash_map::HashMap;
 use std::collections::hash_set::HashSet;
-use std::error::Error;
 
+const DAY: u32 = 7;
+
+/// Builds the bag tree from the list of rules.
 #[aoc_generator(day7)]
-fn parse_input(input: &str) -> Tree {
+pub fn parse_input(input: &str) -> Result<Tree, AocError> {
     let mut tree = Tree::new();
 
-    let rules = input.lines().map(|l| parse_rule(l));
-
-    rules.for_each(|r| tree.insert_rule(r));
+    for (idx, l) in input.lines().enumerate() {
+        let rule = parse_rule(l).map_err(|e| e.offset_lines(idx))?;
+        tree.insert_rule(rule);
+    }
 
-    return tree;
+    Ok(tree)
 }
 
+/// Number of bags that can eventually contain a shiny gold bag.
 #[aoc(day7, part1)]
-fn count_containers(tree: &Tree) -> usize {
-    tree.search_containers("shiny gold").unwrap().len()
+pub fn count_containers(tree: &Tree) -> Result<usize, AocError> {
+    Ok(tree.search_containers("shiny gold")?.len())
 }
 
+/// Number of bags needed inside a shiny gold bag.
 #[aoc(day7, part2)]
-fn count_bags_needed(tree: &Tree) -> usize {
+pub fn count_bags_needed(tree: &Tree) -> Result<usize, AocError> {
     tree.count_needed_bags("shiny gold")
 }
 
 type Rule<'a> = (&'a str, Option<Vec<(usize, String)>>);
 
-struct Bag {
+/// Node of the bag tree, linked to its parents and children by index.
+pub struct Bag {
     idx: usize,
     contained_by: Option<Vec<usize>>,
     contains: Option<Vec<(usize, usize)>>,
@@ -86,12 +93,19 @@ impl Bag {
     }
 }
 
-struct Tree {
+/// Bags and the rules that relate them.
+pub struct Tree {
     nodes: Vec<Bag>,
     len: usize,
     node_index_hash: HashMap<String, usize>,
 }
 
+impl Default for Tree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Tree {
     pub fn new() -> Self {
         Tree {
@@ -145,7 +159,7 @@ impl Tree {
 
     /// Returns a vector of containers that can eventually hold a container
     /// with description @desc
-    pub fn search_containers(&self, desc: &str) -> Result<Vec<&Bag>, Box<dyn Error>> {
+    pub fn search_containers(&self, desc: &str) -> Result<Vec<&Bag>, AocError> {
         let container = self.get(desc);
 
         if let Some(c) = container {
@@ -155,44 +169,92 @@ impl Tree {
             let containers = container_idxs.map(|idx| &self.nodes[*idx]).collect();
             Ok(containers)
         } else {
-            bail!("Bag not found!");
+            Err(bag_not_found(desc))
         }
     }
 
     /// Returns the count of needed bags inside
-    pub fn count_needed_bags(&self, desc: &str) -> usize {
+    pub fn count_needed_bags(&self, desc: &str) -> Result<usize, AocError> {
         let container = self.get(desc);
         if let Some(c) = container {
-            c.count_needed_bags_recursive(self)
+            Ok(c.count_needed_bags_recursive(self))
         } else {
-            unreachable!();
+            Err(bag_not_found(desc))
         }
     }
 }
 
-fn parse_rule(input: &str) -> Rule {
+fn bag_not_found(desc: &str) -> AocError {
+    AocError::no_solution(DAY, format!("there is no rule for {} bags", desc))
+}
+
+/// Parses a single rule, reporting errors as if it was the first line.
+fn parse_rule(input: &str) -> Result<Rule<'_>, AocError> {
     lazy_static! {
         static ref RULE_PARSER: Regex = Regex::new(
             r"(?P<description_container>\w+ \w+) bags contain (?P<description_contained>.*).",
         )
         .unwrap();
     }
-    let captures = RULE_PARSER.captures(input).unwrap();
+    let captures = match RULE_PARSER.captures(input) {
+        Some(captures) => captures,
+        None => {
+            return Err(AocError::parse(
+                DAY,
+                1,
+                1,
+                input,
+                "expected a rule like `<adjective> <color> bags contain ...`",
+            ))
+        }
+    };
 
     let description_container = captures.name("description_container").unwrap();
     let description_contained = captures.name("description_contained").unwrap();
     if description_contained == "no other bags" {
-        return (description_container, None);
+        Ok((description_container, None))
     } else {
         let unparsed: Vec<&str> = description_contained.split(",").map(|r| r.trim()).collect();
         let rules: Vec<(usize, String)> = unparsed
             .iter()
             .map(|u| {
                 let tokens: Vec<&str> = u.split(" ").collect();
-                (tokens[0].parse().unwrap(), tokens[1..3].join(" "))
+                if tokens.len() < 3 {
+                    return Err(AocError::parse(
+                        DAY,
+                        1,
+                        column(input, u),
+                        u,
+                        "expected `<quantity> <adjective> <color> bags`",
+                    ));
+                }
+                Ok((
+                    parse_token(DAY, 0, input, tokens[0])?,
+                    tokens[1..3].join(" "),
+                ))
             })
-            .collect();
-        return (description_container, Some(rules));
+            .collect::<Result<_, AocError>>()?;
+        Ok((description_container, Some(rules)))
+    }
+}
+
+/// Solver for day 7, usable outside of cargo-aoc.
+pub struct Day07;
+
+impl Solver for Day07 {
+    type Input = Tree;
+    type Output = usize;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        parse_input(input)
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        count_containers(input)
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        count_bags_needed(input)
     }
 }
 
@@ -211,14 +273,14 @@ faded blue bags contain no other bags.
 dotted black bags contain no other bags.";
     #[test]
     fn test_search_containers() {
-        let tree = parse_input(EXAMPLE_INPUT);
+        let tree = parse_input(EXAMPLE_INPUT).unwrap();
         let count = tree.search_containers("shiny gold").unwrap();
         assert_eq!(count.len(), 4);
     }
 
     #[test]
     fn test_tree_construction() {
-        let tree = parse_input(EXAMPLE_INPUT);
+        let tree = parse_input(EXAMPLE_INPUT).unwrap();
         let node1 = tree.get("light red").unwrap();
         let node2 = tree.get("bright white").unwrap();
         let node3 = tree.get("muted yellow").unwrap();
@@ -235,16 +297,16 @@ dotted black bags contain no other bags.";
         let s2 = "bright white bags contain 1 shiny gold bag.";
         let s3 = "light red bags contain 1 bright white bag, 2 muted yellow bags.";
 
-        let rule = parse_rule(&s1);
+        let rule = parse_rule(s1).unwrap();
         assert_eq!(rule, ("faded blue", None));
 
-        let rule2 = parse_rule(&s2);
+        let rule2 = parse_rule(s2).unwrap();
         assert_eq!(
             rule2,
             ("bright white", Some(vec![(1, "shiny gold".to_string())]))
         );
 
-        let rule3 = parse_rule(&s3);
+        let rule3 = parse_rule(s3).unwrap();
         assert_eq!(
             rule3,
             (
@@ -257,6 +319,26 @@ dotted black bags contain no other bags.";
         );
     }
 
+    #[test]
+    fn test_parse_errors() {
+        let input =
+            "faded blue bags contain no other bags.\nshiny gold bags contain x dark olive bags.";
+        assert_eq!(
+            parse_input(input).err(),
+            Some(AocError::parse(
+                7,
+                2,
+                25,
+                "x",
+                "invalid digit found in string"
+            ))
+        );
+        assert!(parse_rule("shiny gold bags").is_err());
+        assert!(parse_rule("shiny gold bags contain 1 olive.").is_err());
+        let tree = parse_input("faded blue bags contain no other bags.").unwrap();
+        assert!(count_containers(&tree).is_err());
+    }
+
     #[test]
     fn test_regex() {
         let instruction_parser: Regex = Regex::new(
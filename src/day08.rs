@@ -1,12 +1,20 @@
+use crate::error::column;
+use crate::stream::records;
+use crate::{AocError, Solver, StreamSolver};
 use aoc_runner_derive::aoc;
-use simple_error::{bail, SimpleError};
 use std::collections::HashSet;
-use std::error::Error;
+use std::io::BufRead;
 use std::num::ParseIntError;
 
-fn load_assembler(input: &str) -> Result<Assembler, Box<dyn Error>> {
+const DAY: u32 = 8;
+
+fn load_assembler(input: &str) -> Result<Assembler, AocError> {
+    read_assembler(input.as_bytes())
+}
+
+fn read_assembler<R: BufRead>(reader: R) -> Result<Assembler, AocError> {
     let mut assembler = Assembler::new();
-    assembler.load_program(input)?;
+    assembler.load_program(reader)?;
     return Ok(assembler);
 }
 
@@ -16,24 +24,28 @@ enum AssemblerResult {
     InfiniteLoopErr,
 }
 
+/// Value of the accumulator right before an instruction is executed twice.
 #[aoc(day8, part1)]
-fn exec_until_inf_loop(input: &str) -> Result<i64, Box<dyn Error>> {
-    let mut assembler = Assembler::new();
-    assembler.load_program(input)?;
+pub fn exec_until_inf_loop(input: &str) -> Result<i64, AocError> {
+    run_until_loop(load_assembler(input)?)
+}
+
+fn run_until_loop(mut assembler: Assembler) -> Result<i64, AocError> {
     loop {
         assembler.execute_next_instruction()?;
         if assembler.check_executed(assembler.instruction_pointer)? {
             return Ok(assembler.global_counter);
-        } else {
-            ()
         }
     }
-    bail!("Logical error");
 }
 
+/// Value of the accumulator once the corrupted instruction is fixed and the program terminates.
 #[aoc(day8, part2)]
-fn part_2(input: &str) -> i64 {
-    let assembler = load_assembler(input).unwrap();
+pub fn part_2(input: &str) -> Result<i64, AocError> {
+    fix_program(load_assembler(input)?)
+}
+
+fn fix_program(assembler: Assembler) -> Result<i64, AocError> {
     let first_clone = assembler.clone();
     let mut count_jmp_nop_occ: HashSet<usize> = HashSet::new();
 
@@ -56,26 +68,21 @@ fn part_2(input: &str) -> i64 {
         Instruction::ACC(v) => Instruction::ACC(v),
     };
 
-    let mut ret = 0;
-
     for idx in count_jmp_nop_occ.iter() {
         let mut cloned = assembler.clone();
         assert!(idx < &cloned.program_len);
-        let inst = assembler.get_instruction_at_idx(*idx).unwrap();
-        cloned
-            .set_instruction_at_idx(*idx, switch_op(*inst))
-            .unwrap();
-        let result = cloned.execute_program().unwrap();
-        match result {
-            AssemblerResult::Acc(v) => {
-                ret = v;
-                break;
-            }
-            AssemblerResult::InfiniteLoopErr => (),
+        let inst = assembler.get_instruction_at_idx(*idx)?;
+        cloned.set_instruction_at_idx(*idx, switch_op(*inst))?;
+        // A fix that makes the program jump outside of itself is not the right one either
+        if let Ok(AssemblerResult::Acc(v)) = cloned.execute_program() {
+            return Ok(v);
         }
     }
 
-    return ret;
+    Err(AocError::no_solution(
+        DAY,
+        "no single jmp/nop switch makes the program terminate",
+    ))
 }
 
 #[derive(Clone)]
@@ -98,9 +105,9 @@ impl Assembler {
         }
     }
 
-    pub fn execute_program(&mut self) -> Result<AssemblerResult, Box<dyn Error>> {
+    pub fn execute_program(&mut self) -> Result<AssemblerResult, AocError> {
         if self.loaded_program.is_none() {
-            bail!("Program not loaded");
+            return Err(no_program_loaded());
         }
 
         loop {
@@ -122,44 +129,63 @@ impl Assembler {
         &mut self,
         idx: usize,
         instruction: Instruction,
-    ) -> Result<(), SimpleError> {
+    ) -> Result<(), AocError> {
         if let Some(instructions) = &mut self.loaded_program {
             instructions[idx] = instruction;
             Ok(())
         } else {
-            bail!("No program loaded");
+            Err(no_program_loaded())
         }
     }
-    pub fn get_instruction_at_idx(&self, idx: usize) -> Result<&Instruction, SimpleError> {
+    pub fn get_instruction_at_idx(&self, idx: usize) -> Result<&Instruction, AocError> {
         if let Some(instructions) = &self.loaded_program {
             Ok(&instructions[idx])
         } else {
-            bail!("No program loaded");
+            Err(no_program_loaded())
         }
     }
 
-    pub fn parse_instruction(instruction: &str) -> Result<Instruction, Box<dyn Error>> {
+    /// Parses a single instruction, reporting errors as if it was the first line.
+    pub fn parse_instruction(instruction: &str) -> Result<Instruction, AocError> {
         let splitted: Vec<&str> = instruction.split_whitespace().collect();
+        if splitted.len() != 2 {
+            return Err(AocError::parse(
+                DAY,
+                1,
+                1,
+                instruction,
+                "expected an operation and its argument",
+            ));
+        }
+        let argument = parse_int(splitted[1]).map_err(|e| {
+            AocError::parse(DAY, 1, column(instruction, splitted[1]), splitted[1], e)
+        })?;
         let instruction = match splitted[0] {
-            "acc" => Instruction::ACC(parse_int(splitted[1])?),
-            "jmp" => Instruction::JMP(parse_int(splitted[1])?),
-            "nop" => Instruction::NOP(parse_int(splitted[1])?),
-            _ => unreachable!(),
+            "acc" => Instruction::ACC(argument),
+            "jmp" => Instruction::JMP(argument),
+            "nop" => Instruction::NOP(argument),
+            op => {
+                return Err(AocError::parse(
+                    DAY,
+                    1,
+                    column(instruction, op),
+                    op,
+                    "expected `acc`, `jmp` or `nop`",
+                ))
+            }
         };
 
         Ok(instruction)
     }
 
-    pub fn load_program(&mut self, input: &str) -> Result<(), Box<dyn Error>> {
-        let lines = input.lines();
-        let mut program = vec![];
-        let mut program_len = 0;
-
-        for line in lines {
-            let instruction = Assembler::parse_instruction(line)?;
-            program.push(instruction);
-            program_len += 1;
-        }
+    /// Loads the program one line at a time out of `reader`, keeping only
+    /// the decoded instructions.
+    pub fn load_program<R: BufRead>(&mut self, reader: R) -> Result<(), AocError> {
+        let program: Vec<Instruction> = records(reader, |idx, line| {
+            Assembler::parse_instruction(line).map_err(|e| e.offset_lines(idx))
+        })
+        .collect::<Result<_, AocError>>()?;
+        let program_len = program.len();
 
         self.program_len = program_len;
         self.loaded_program = Some(program);
@@ -169,25 +195,25 @@ impl Assembler {
         Ok(())
     }
 
-    pub fn set_executed(&mut self, idx: usize) -> Result<(), SimpleError> {
+    pub fn set_executed(&mut self, idx: usize) -> Result<(), AocError> {
         if idx >= self.program_len {
-            bail!("Out of bounds  se instruction")
+            return Err(out_of_bounds(idx));
         }
 
         match &mut self.executed_flag {
             Some(ef) => Ok(ef[idx] = true),
-            None => bail!("No program loaded!"),
+            None => Err(no_program_loaded()),
         }
     }
 
-    pub fn check_executed(&self, idx: usize) -> Result<bool, SimpleError> {
+    pub fn check_executed(&self, idx: usize) -> Result<bool, AocError> {
         if idx >= self.program_len {
-            bail!("Out of bounds  ce instruction")
+            return Err(out_of_bounds(idx));
         }
 
         match &self.executed_flag {
             Some(v) => Ok(v[idx]),
-            None => bail!("No program loaded!"),
+            None => Err(no_program_loaded()),
         }
     }
 
@@ -197,48 +223,57 @@ impl Assembler {
         self.instruction_pointer = 0;
     }
 
-    fn execute_next_instruction(&mut self) -> Result<Option<Instruction>, SimpleError> {
+    fn execute_next_instruction(&mut self) -> Result<Option<Instruction>, AocError> {
         if self.instruction_pointer >= self.program_len {
-            bail!("Unexpected EOF");
+            return Err(AocError::no_solution(
+                DAY,
+                "the program ran past its last instruction",
+            ));
         }
 
-        let mut result: Option<(usize, i64)> = None;
-
-        if let Some(instructions) = &self.loaded_program {
-            result = match instructions[self.instruction_pointer] {
+        let (new_ins_ptr, new_global_ctr) = if let Some(instructions) = &self.loaded_program {
+            match instructions[self.instruction_pointer] {
                 Instruction::JMP(v) => {
                     let new_ins_ptr: i64 = (self.instruction_pointer as i64) + v;
-                    Some((new_ins_ptr as usize, self.global_counter))
-                }
-                Instruction::NOP(_v) => Some((self.instruction_pointer + 1, self.global_counter)),
-                Instruction::ACC(v) => {
-                    Some((self.instruction_pointer + 1, self.global_counter + v))
+                    (new_ins_ptr as usize, self.global_counter)
                 }
-            };
+                Instruction::NOP(_v) => (self.instruction_pointer + 1, self.global_counter),
+                Instruction::ACC(v) => (self.instruction_pointer + 1, self.global_counter + v),
+            }
         } else {
-            bail!("Program not loaded yet");
-        }
+            return Err(no_program_loaded());
+        };
 
-        if let Some((new_ins_ptr, new_global_ctr)) = result {
-            if new_ins_ptr == self.program_len {
-                self.set_executed(self.instruction_pointer)?;
-                self.global_counter = new_global_ctr;
-                Ok(None)
-            } else if new_ins_ptr > self.program_len {
-                bail!("Invalid new idx");
-            } else {
-                self.set_executed(self.instruction_pointer)?;
-                self.instruction_pointer = new_ins_ptr;
-                self.global_counter = new_global_ctr;
-                let instructions = self.loaded_program.as_ref().unwrap();
-                Ok(Some(instructions[new_ins_ptr].clone()))
-            }
+        if new_ins_ptr == self.program_len {
+            self.set_executed(self.instruction_pointer)?;
+            self.global_counter = new_global_ctr;
+            Ok(None)
+        } else if new_ins_ptr > self.program_len {
+            Err(AocError::no_solution(
+                DAY,
+                format!(
+                    "instruction {} jumps outside of the program",
+                    self.instruction_pointer + 1
+                ),
+            ))
         } else {
-            bail!("Unexpected error occurred");
+            self.set_executed(self.instruction_pointer)?;
+            self.instruction_pointer = new_ins_ptr;
+            self.global_counter = new_global_ctr;
+            let instructions = self.loaded_program.as_ref().unwrap();
+            Ok(Some(instructions[new_ins_ptr]))
         }
     }
 }
 
+fn no_program_loaded() -> AocError {
+    AocError::invalid_argument("no program loaded")
+}
+
+fn out_of_bounds(idx: usize) -> AocError {
+    AocError::invalid_argument(format!("instruction {} is out of bounds", idx))
+}
+
 pub fn parse_int(input: &str) -> Result<i64, ParseIntError> {
     i64::from_str_radix(input, 10)
 }
@@ -250,6 +285,37 @@ enum Instruction {
     ACC(i64),
 }
 
+/// Solver for day 8, usable outside of cargo-aoc.
+pub struct Day08;
+
+impl Solver for Day08 {
+    type Input = String;
+    type Output = i64;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        Ok(input.to_string())
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        exec_until_inf_loop(input)
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        part_2(input)
+    }
+}
+
+// Jumps go both ways, so the whole program is kept, but not its text
+impl StreamSolver for Day08 {
+    fn part1_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        run_until_loop(read_assembler(reader)?)
+    }
+
+    fn part2_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        fix_program(read_assembler(reader)?)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -272,15 +338,41 @@ acc +6";
 
     #[test]
     fn test_part_2() {
-        part_2(EXAMPLE_PROGRAM);
+        part_2(EXAMPLE_PROGRAM).unwrap();
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            load_assembler("nop +0\nacc +1\nadd +4").err(),
+            Some(AocError::parse(
+                8,
+                3,
+                1,
+                "add",
+                "expected `acc`, `jmp` or `nop`"
+            ))
+        );
+        assert_eq!(
+            load_assembler("nop +0\njmp +x").err(),
+            Some(AocError::parse(
+                8,
+                2,
+                5,
+                "+x",
+                "invalid digit found in string"
+            ))
+        );
+        assert!(load_assembler("nop").is_err());
+        assert!(exec_until_inf_loop("jmp +2").is_err());
     }
 
     #[test]
     fn test_instructions() {
         let mut assembler = load_assembler(EXAMPLE_PROGRAM).unwrap();
-        assembler.execute_next_instruction();
-        assembler.execute_next_instruction();
-        assembler.execute_next_instruction();
+        assembler.execute_next_instruction().unwrap();
+        assembler.execute_next_instruction().unwrap();
+        assembler.execute_next_instruction().unwrap();
         assert_eq!(assembler.instruction_pointer, 6);
         assert_eq!(assembler.global_counter, 1);
     }
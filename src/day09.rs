@@ -1,18 +1,77 @@
-use aoc_runner_derive::{aoc, aoc_generator};
+use crate::error::parse_token;
+use crate::stream::records;
+use crate::{AocError, Solver, StreamSolver};
+use aoc_runner_derive::aoc;
+use std::collections::{HashSet, VecDeque};
+use std::io::BufRead;
 
+const DAY: u32 = 9;
+
+/// First number that is not the sum of two of the 25 numbers before it.
 #[aoc(day9, part1)]
-fn find_outlier(input: &str) -> isize {
-    let cypher = Cypher::from_capacity(25, input);
-    cypher.find_outlier().unwrap()
+pub fn find_outlier(input: &str) -> Result<isize, AocError> {
+    read_outlier(25, input.as_bytes())
+}
+
+/// First outlier of the numbers of `reader`, keeping only the last
+/// `capacity` numbers around.
+pub fn read_outlier<R: BufRead>(capacity: usize, reader: R) -> Result<isize, AocError> {
+    if capacity == 0 {
+        return Err(empty_preamble());
+    }
+    let mut preamble = VecDeque::with_capacity(capacity);
+    for number in read_numbers(reader) {
+        let number = number?;
+        if preamble.len() == capacity {
+            if pair_summing_to(preamble.make_contiguous(), number).is_none() {
+                return Ok(number);
+            }
+            preamble.pop_front();
+        }
+        preamble.push_back(number);
+    }
+
+    Err(no_outlier())
 }
 
+/// Parses the numbers one line at a time out of `reader`.
+pub fn read_numbers<R: BufRead>(reader: R) -> impl Iterator<Item = Result<isize, AocError>> {
+    records(reader, |idx, x| parse_token(DAY, idx, x, x))
+}
+
+/// Sum of the smallest and largest numbers of the contiguous run adding up to the outlier.
 #[aoc(day9, part2)]
-fn find_contiguous(input: &str) -> isize {
-    let cypher = Cypher::from_capacity(25, input);
-    let slice = cypher.find_contiguous(375054920).unwrap();
+pub fn find_contiguous(input: &str) -> Result<isize, AocError> {
+    contiguous_sum(Cypher::from_capacity(25, input)?)
+}
+
+fn contiguous_sum(cypher: Cypher) -> Result<isize, AocError> {
+    let outlier = cypher.find_outlier().ok_or_else(no_outlier)?;
+    let slice = cypher.find_contiguous(outlier).ok_or_else(|| {
+        AocError::no_solution(DAY, format!("no contiguous run adds up to {}", outlier))
+    })?;
     let mut numbers = slice.to_vec();
     numbers.sort();
-    return numbers[0] + numbers[numbers.len() - 1];
+    Ok(numbers[0] + numbers[numbers.len() - 1])
+}
+
+fn no_outlier() -> AocError {
+    AocError::no_solution(DAY, "every number is the sum of two of the previous ones")
+}
+
+fn empty_preamble() -> AocError {
+    AocError::invalid_argument("the preamble cannot be empty")
+}
+
+/// Two numbers of `preamble` adding up to `num`, which may be the same one
+/// twice.
+fn pair_summing_to(preamble: &[isize], num: isize) -> Option<(isize, isize)> {
+    let values: HashSet<isize> = preamble.iter().copied().collect();
+
+    preamble.iter().find_map(|x| {
+        let y = num.checked_sub(*x)?;
+        values.contains(&y).then_some((*x, y))
+    })
 }
 
 struct Cypher {
@@ -22,61 +81,40 @@ struct Cypher {
 }
 
 impl Cypher {
-    pub fn from_capacity(capacity: usize, input: &str) -> Self {
-        let mut lines: Vec<&str> = input.lines().collect::<Vec<&str>>();
-        let len = lines.len();
-        let numbers: Vec<isize> = lines.iter().map(|x| x.parse().unwrap()).collect();
-        Cypher {
+    pub fn from_capacity(capacity: usize, input: &str) -> Result<Self, AocError> {
+        Cypher::from_reader(capacity, input.as_bytes())
+    }
+
+    pub fn from_reader<R: BufRead>(capacity: usize, reader: R) -> Result<Self, AocError> {
+        if capacity == 0 {
+            return Err(empty_preamble());
+        }
+        let numbers: Vec<isize> = read_numbers(reader).collect::<Result<_, AocError>>()?;
+        let len = numbers.len();
+        Ok(Cypher {
             numbers,
             capacity,
             len,
-        }
+        })
     }
 
+    /// Two numbers of the preamble adding up to `num`, the first
+    /// number past the preamble with that value.
     pub fn valid_number(&self, num: isize) -> Option<(isize, isize)> {
-        let mut found = false;
-        let (mut idx_i, mut idx_j) = (0, 0);
-        let mut found_number = false;
-        let mut idx_found = 0;
-
-        // Find idx in list number
-        for (idx, x) in self.numbers.iter().enumerate() {
-            if *x == num && idx > self.capacity - 1 {
-                found_number = true;
-                idx_found = idx;
-                break;
-            }
-        }
-
-        if !found_number {
-            return None;
-        }
-        for i in idx_found - self.capacity..idx_found {
-            for j in idx_found - self.capacity..idx_found {
-                if num == self.numbers[i] + self.numbers[j] {
-                    idx_i = i;
-                    idx_j = j;
-                    found = true;
-                    break;
-                }
-            }
-        }
+        let idx = (self.capacity..self.len).find(|idx| self.numbers[*idx] == num)?;
+        self.valid_at(idx)
+    }
 
-        if found {
-            Some((self.numbers[idx_i], self.numbers[idx_j]))
-        } else {
-            None
-        }
+    /// Two numbers of the preamble adding up to the number at
+    /// `idx`, which has to be past the first preamble.
+    fn valid_at(&self, idx: usize) -> Option<(isize, isize)> {
+        pair_summing_to(&self.numbers[idx - self.capacity..idx], self.numbers[idx])
     }
 
     pub fn find_outlier(&self) -> Option<isize> {
-        for n in self.numbers[self.capacity..].iter() {
-            if self.valid_number(*n).is_none() {
-                return Some(*n);
-            }
-        }
-
-        None
+        (self.capacity..self.len)
+            .find(|idx| self.valid_at(*idx).is_none())
+            .map(|idx| self.numbers[idx])
     }
 
     pub fn find_contiguous(&self, target: isize) -> Option<&[isize]> {
@@ -122,14 +160,47 @@ impl Cypher {
     }
 }
 
+/// Solver for day 9, usable outside of cargo-aoc.
+pub struct Day09;
+
+impl Solver for Day09 {
+    type Input = String;
+    type Output = isize;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        Ok(input.to_string())
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        find_outlier(input)
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        find_contiguous(input)
+    }
+}
+
+// The run adding up to the outlier may start anywhere before it, so part 2
+// keeps the numbers, but not their text
+impl StreamSolver for Day09 {
+    fn part1_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        read_outlier(25, reader)
+    }
+
+    fn part2_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        contiguous_sum(Cypher::from_reader(25, reader)?)
+    }
+}
+
 #[cfg(test)]
 pub mod test {
     const EXAMPLE_INPUT: &str =
         "35\n20\n15\n25\n47\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n219\n299\n277\n309\n576";
     use super::*;
+    use proptest::prelude::*;
     #[test]
     fn test_cypher() {
-        let cypher = Cypher::from_capacity(5, EXAMPLE_INPUT);
+        let cypher = Cypher::from_capacity(5, EXAMPLE_INPUT).unwrap();
         assert_eq!(cypher.valid_number(127), None);
         assert!(cypher.valid_number(40).is_some());
         assert!(cypher.valid_number(62).is_some());
@@ -139,15 +210,131 @@ pub mod test {
 
     #[test]
     fn test_find_outlier() {
-        let cypher = Cypher::from_capacity(5, EXAMPLE_INPUT);
+        let cypher = Cypher::from_capacity(5, EXAMPLE_INPUT).unwrap();
         assert_eq!(cypher.find_outlier(), Some(127));
+        assert_eq!(read_outlier(5, EXAMPLE_INPUT.as_bytes()), Ok(127));
+        // Lines past the outlier are never read
+        assert_eq!(read_outlier(2, "1\n2\n3\n9\nx".as_bytes()), Ok(9));
+        assert!(read_outlier(0, EXAMPLE_INPUT.as_bytes()).is_err());
     }
 
     #[test]
     fn test_contiguous() {
-        let cypher = Cypher::from_capacity(5, EXAMPLE_INPUT);
+        let cypher = Cypher::from_capacity(5, EXAMPLE_INPUT).unwrap();
         let contiguous = cypher.find_contiguous(127);
         let slice = [15isize, 25, 47, 40];
         assert_eq!(contiguous, Some(&slice[..]));
     }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Cypher::from_capacity(5, "35\n2o").err(),
+            Some(AocError::parse(
+                9,
+                2,
+                1,
+                "2o",
+                "invalid digit found in string"
+            ))
+        );
+        assert!(Cypher::from_capacity(0, EXAMPLE_INPUT).is_err());
+        assert!(find_outlier("1\n2\n3").is_err());
+    }
+
+    /// The original `Cypher::valid_number`, loops and all, looking `num`
+    /// up past the first `capacity` of `numbers`.
+    fn original_valid_number(
+        numbers: &[isize],
+        capacity: usize,
+        num: isize,
+    ) -> Option<(isize, isize)> {
+        let mut found = false;
+        let (mut idx_i, mut idx_j) = (0, 0);
+        let mut found_number = false;
+        let mut idx_found = 0;
+
+        // Find idx in list number
+        for (idx, x) in numbers.iter().enumerate() {
+            if *x == num && idx > capacity - 1 {
+                found_number = true;
+                idx_found = idx;
+                break;
+            }
+        }
+
+        if !found_number {
+            return None;
+        }
+        for i in idx_found - capacity..idx_found {
+            for j in idx_found - capacity..idx_found {
+                if num == numbers[i] + numbers[j] {
+                    idx_i = i;
+                    idx_j = j;
+                    found = true;
+                    break;
+                }
+            }
+        }
+
+        if found {
+            Some((numbers[idx_i], numbers[idx_j]))
+        } else {
+            None
+        }
+    }
+
+    /// The original `Cypher::find_outlier`.
+    fn original_find_outlier(numbers: &[isize], capacity: usize) -> Option<isize> {
+        for n in numbers[capacity..].iter() {
+            if original_valid_number(numbers, capacity, *n).is_none() {
+                return Some(*n);
+            }
+        }
+
+        None
+    }
+
+    fn distinct_numbers() -> impl Strategy<Value = Vec<isize>> {
+        // The original looked numbers up by value, so a repeated number was
+        // checked against the preamble of its first occurrence
+        proptest::collection::hash_set(0..60isize, 1..40)
+            .prop_map(|numbers| numbers.into_iter().collect::<Vec<_>>())
+            .prop_shuffle()
+    }
+
+    proptest! {
+        #[test]
+        fn test_valid_matches_reference(
+            capacity in 1..6usize,
+            numbers in distinct_numbers(),
+        ) {
+            prop_assume!(capacity < numbers.len());
+            let input = numbers.iter().map(|n| n.to_string()).collect::<Vec<_>>().join("\n");
+            let cypher = Cypher::from_capacity(capacity, &input).unwrap();
+            for num in &numbers[capacity..] {
+                let valid = cypher.valid_number(*num);
+                let original = original_valid_number(&numbers, capacity, *num);
+                prop_assert_eq!(valid.is_some(), original.is_some());
+                if let Some((a, b)) = valid {
+                    prop_assert_eq!(a + b, *num);
+                }
+            }
+            prop_assert_eq!(cypher.find_outlier(), original_find_outlier(&numbers, capacity));
+        }
+    }
+
+    #[test]
+    fn test_pair_with_itself() {
+        // Like in the original, `4` is the sum of the `2` before it twice
+        let cypher = Cypher::from_capacity(2, "2\n7\n4").unwrap();
+        assert_eq!(cypher.valid_number(4), Some((2, 2)));
+        assert_eq!(cypher.find_outlier(), None);
+        assert_eq!(pair_summing_to(&[isize::MIN, -1], isize::MAX), None);
+        assert_eq!(pair_summing_to(&[isize::MAX, -1], isize::MIN), None);
+        assert_eq!(
+            pair_summing_to(&[isize::MAX, -1], isize::MAX - 1),
+            Some((isize::MAX, -1))
+        );
+    }
 }
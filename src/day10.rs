@@ -1,32 +1,58 @@
+use crate::error::parse_token;
+use crate::stream::records;
+use crate::{AocError, Solver, StreamSolver};
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::io::BufRead;
 
+const DAY: u32 = 10;
+
+/// Sorts the adapters, adding the outlet and the device.
 #[aoc_generator(day10)]
-fn parse_input(input: &str) -> Vec<usize> {
-    let mut vec: Vec<usize> = input.lines().map(|l| l.parse().unwrap()).collect();
+pub fn parse_input(input: &str) -> Result<Vec<usize>, AocError> {
+    read_adapters(input.as_bytes())
+}
+
+/// Reads the adapters one line at a time out of `reader`, sorting them like
+/// `parse_input` does.
+pub fn read_adapters<R: BufRead>(reader: R) -> Result<Vec<usize>, AocError> {
+    let mut vec: Vec<usize> =
+        records(reader, |idx, l| parse_token(DAY, idx, l, l)).collect::<Result<_, AocError>>()?;
     vec.sort();
-    let highest = vec.last().unwrap();
+    let highest = vec.last().copied().unwrap_or(0);
     vec.push(highest + 3);
     vec.insert(0, 0);
-    return vec;
+    Ok(vec)
 }
 
+/// Number of 1-jolt differences times the number of 3-jolt differences.
 #[aoc(day10, part1)]
-fn part_1(input: &[usize]) -> usize {
+pub fn part_1(input: &[usize]) -> Result<usize, AocError> {
     let mut count_1 = 0;
     let mut count_3 = 0;
     for i in 0..input.len() - 1 {
         match input[i + 1] - input[i] {
             1 => count_1 += 1,
+            2 => (),
             3 => count_3 += 1,
-            _ => unreachable!(),
+            _ => {
+                return Err(AocError::no_solution(
+                    DAY,
+                    format!(
+                        "adapters {} and {} cannot be chained",
+                        input[i],
+                        input[i + 1]
+                    ),
+                ))
+            }
         }
     }
 
-    return count_3 * count_1;
+    Ok(count_3 * count_1)
 }
 
+/// Number of distinct adapter arrangements.
 #[aoc(day10, part2)]
-fn part_2(input: &[usize]) -> usize {
+pub fn part_2(input: &[usize]) -> usize {
     let current_vec = input;
     let len = current_vec.len();
     let mut pivots = vec![];
@@ -71,6 +97,37 @@ fn count_inner(slice: &[usize], pivot_idx: usize) -> usize {
     }
 }
 
+/// Solver for day 10, usable outside of cargo-aoc.
+pub struct Day10;
+
+impl Solver for Day10 {
+    type Input = Vec<usize>;
+    type Output = usize;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        parse_input(input)
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        part_1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(part_2(input))
+    }
+}
+
+// Both parts need the adapters sorted, so only the text is dropped
+impl StreamSolver for Day10 {
+    fn part1_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        part_1(&read_adapters(reader)?)
+    }
+
+    fn part2_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        Ok(part_2(&read_adapters(reader)?))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -79,15 +136,31 @@ mod test {
 
     #[test]
     fn test_example() {
-        let vals = parse_input(EXAMPLE_INPUT);
-        let part_1 = part_1(&vals);
+        let vals = parse_input(EXAMPLE_INPUT).unwrap();
+        let part_1 = part_1(&vals).unwrap();
         assert_eq!(part_1, 35);
     }
 
     #[test]
     fn test_example_p2() {
-        let vals = parse_input(EXAMPLE_INPUT);
+        let vals = parse_input(EXAMPLE_INPUT).unwrap();
         let count = part_2(&vals);
         assert_eq!(count, 8);
     }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            parse_input("16\n-10").err(),
+            Some(AocError::parse(
+                10,
+                2,
+                1,
+                "-10",
+                "invalid digit found in string"
+            ))
+        );
+        let vals = parse_input("1\n8").unwrap();
+        assert!(part_1(&vals).is_err());
+    }
 }
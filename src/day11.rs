@@ -1,20 +1,52 @@
+use crate::{AocError, Solver};
 use aoc_runner_derive::{aoc, aoc_generator};
 
+const DAY: u32 = 11;
+
+/// Parses the seat layout.
 #[aoc_generator(day11)]
-fn parse_input(input: &str) -> Vec<Vec<char>> {
+pub fn parse_input(input: &str) -> Result<Vec<Vec<char>>, AocError> {
     let mut seats: Vec<Vec<char>> = Vec::new();
 
-    for line in input.lines() {
-        let chars = line.chars().collect();
+    for (i, line) in input.lines().enumerate() {
+        let chars: Vec<char> = line.chars().collect();
+        if let Some(j) = chars.iter().position(|c| !"L.#".contains(*c)) {
+            let text = chars[j].to_string();
+            return Err(AocError::parse(
+                DAY,
+                i + 1,
+                j + 1,
+                &text,
+                "expected `L`, `.` or `#`",
+            ));
+        }
+        if let Some(first) = seats.first() {
+            if first.len() != chars.len() {
+                let reason = format!("expected a row of {} seats", first.len());
+                return Err(AocError::parse(DAY, i + 1, 1, line, reason));
+            }
+        }
         seats.push(chars);
     }
 
-    return seats;
+    // The neighbour lookups assume there is at least one seat on every side of a corner
+    if seats.len() < 2 || seats[0].len() < 2 {
+        return Err(AocError::parse(
+            DAY,
+            1,
+            1,
+            input,
+            "expected at least 2x2 seats",
+        ));
+    }
+
+    Ok(seats)
 }
 
+/// Occupied seats once the layout stabilizes looking at adjacent seats.
 #[aoc(day11, part1)]
-fn part_1(seats: &Vec<Vec<char>>) -> usize {
-    let mut new_seats = seats.clone();
+pub fn part_1(seats: &[Vec<char>]) -> usize {
+    let mut new_seats = seats.to_vec();
 
     loop {
         let switch_results = switch_seats(&new_seats);
@@ -38,9 +70,10 @@ fn part_1(seats: &Vec<Vec<char>>) -> usize {
     return occuppied_count;
 }
 
+/// Occupied seats once the layout stabilizes looking at the first visible seats.
 #[aoc(day11, part2)]
-fn part_2(seats: &Vec<Vec<char>>) -> usize {
-    let mut new_seats = seats.clone();
+pub fn part_2(seats: &[Vec<char>]) -> usize {
+    let mut new_seats = seats.to_vec();
     loop {
         let switch_results = switch_seats_p2(&new_seats);
         let switched = switch_results.1;
@@ -94,7 +127,7 @@ fn switch_seat(seats: &Vec<Vec<char>>, i: usize, j: usize) -> (bool, char) {
     }
 }
 
-fn switch_seats(seats: &Vec<Vec<char>>) -> (Vec<Vec<char>>, bool) {
+pub(crate) fn switch_seats(seats: &Vec<Vec<char>>) -> (Vec<Vec<char>>, bool) {
     let m = seats.len();
     let n = seats[0].len();
     let mut new_seating = seats.clone();
@@ -413,6 +446,26 @@ pub fn count_occuppied_p2(
     occuppied_count
 }
 
+/// Solver for day 11, usable outside of cargo-aoc.
+pub struct Day11;
+
+impl Solver for Day11 {
+    type Input = Vec<Vec<char>>;
+    type Output = usize;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        parse_input(input)
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(part_1(input))
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(part_2(input))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -472,9 +525,9 @@ LLL####LL#
 #.L####.L#";
     #[test]
     fn test_switch_seats() {
-        let seats = parse_input(EXAMPLE_INPUT);
-        let expected_output = parse_input(EXAMPLE_OUTPUT_1);
-        let expected_output2 = parse_input(EXAMPLE_OUTPUT_2);
+        let seats = parse_input(EXAMPLE_INPUT).unwrap();
+        let expected_output = parse_input(EXAMPLE_OUTPUT_1).unwrap();
+        let expected_output2 = parse_input(EXAMPLE_OUTPUT_2).unwrap();
         let (new_seats, switched) = switch_seats(&seats);
         let (new_seats2, switched2) = switch_seats(&new_seats);
         assert!(switched);
@@ -491,10 +544,10 @@ LLL####LL#
 
     #[test]
     fn test_p2() {
-        let seats = parse_input(EXAMPLE_INPUT);
-        let expected_output1 = parse_input(EXAMPLE_OUTPUT_1);
-        let expected_output2 = parse_input(EXAMPLE_OUTPUT_2_P2);
-        let expected_output3 = parse_input(EXAMPLE_OUTPUT_3_P2);
+        let seats = parse_input(EXAMPLE_INPUT).unwrap();
+        let expected_output1 = parse_input(EXAMPLE_OUTPUT_1).unwrap();
+        let expected_output2 = parse_input(EXAMPLE_OUTPUT_2_P2).unwrap();
+        let expected_output3 = parse_input(EXAMPLE_OUTPUT_3_P2).unwrap();
         let (output1, switched1) = switch_seats_p2(&seats);
         let (output2, switched2) = switch_seats_p2(&output1);
         let (output3, switched3) = switch_seats_p2(&output2);
@@ -507,12 +560,22 @@ LLL####LL#
 
     #[test]
     fn test_count_occuppied() {
-        let seats = parse_input(EXAMPLE_INPUT);
-        let expected_output1 = parse_input(EXAMPLE_OUTPUT_1);
-        let expected_output2 = parse_input(EXAMPLE_OUTPUT_2_P2);
-        let expected_output3 = parse_input(EXAMPLE_OUTPUT_3_P2);
+        let seats = parse_input(EXAMPLE_INPUT).unwrap();
+        let expected_output1 = parse_input(EXAMPLE_OUTPUT_1).unwrap();
+        let expected_output2 = parse_input(EXAMPLE_OUTPUT_2_P2).unwrap();
+        let expected_output3 = parse_input(EXAMPLE_OUTPUT_3_P2).unwrap();
         let m = seats.len();
         let n = seats[0].len();
         assert_eq!(count_occuppied_p2(&expected_output2, (1, 3), (m, n)), 0);
     }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_input("L.L\nL#x").err(),
+            Some(AocError::parse(11, 2, 3, "x", "expected `L`, `.` or `#`"))
+        );
+        assert!(parse_input("L.L\nL.").is_err());
+        assert!(parse_input("L.LL").is_err());
+    }
 }
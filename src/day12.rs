@@ -1,78 +1,125 @@
+use crate::error::parse_token;
+use crate::stream::records;
+use crate::{AocError, Solver, StreamSolver};
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::num::ParseIntError;
+use std::io::BufRead;
 
+const DAY: u32 = 12;
+
+/// Parses the navigation instructions.
 #[aoc_generator(day12)]
-fn parse_instructions(input: &str) -> Result<Vec<Instruction>, ParseIntError> {
-    let lines = input.lines();
-    let mut instructions = vec![];
-    for line in lines {
-        let chars: Vec<char> = line.chars().collect();
-        let direction = chars[0];
-        let val = &line[1..];
-        let parsed_val: isize = val.parse()?;
-
-        assert!(parsed_val > 0);
-
-        let instruction = match direction {
-            'N' => Instruction::N(parsed_val),
-            'S' => Instruction::S(parsed_val),
-            'E' => Instruction::E(parsed_val),
-            'W' => Instruction::W(parsed_val),
-            'L' => Instruction::L(parsed_val as usize),
-            'R' => Instruction::R(parsed_val as usize),
-            'F' => Instruction::F(parsed_val as usize),
-            _ => unreachable!(),
-        };
+pub fn parse_instructions(input: &str) -> Result<Vec<Instruction>, AocError> {
+    read_instructions(input.as_bytes()).collect()
+}
+
+/// Parses the instructions one line at a time out of `reader`.
+pub fn read_instructions<R: BufRead>(
+    reader: R,
+) -> impl Iterator<Item = Result<Instruction, AocError>> {
+    records(reader, parse_instruction)
+}
 
-        instructions.push(instruction);
+/// Parses the `idx`-th (0-based) instruction.
+fn parse_instruction(idx: usize, line: &str) -> Result<Instruction, AocError> {
+    let mut chars = line.chars();
+    let direction = match chars.next() {
+        Some(c) => c,
+        None => return Err(AocError::parse(DAY, idx + 1, 1, line, "empty instruction")),
+    };
+    let val = chars.as_str();
+    let parsed_val: isize = parse_token(DAY, idx, line, val)?;
+
+    if parsed_val <= 0 {
+        return Err(AocError::parse(
+            DAY,
+            idx + 1,
+            2,
+            val,
+            "expected a positive value",
+        ));
     }
+    if "LR".contains(direction) && parsed_val % 90 != 0 {
+        return Err(AocError::parse(
+            DAY,
+            idx + 1,
+            2,
+            val,
+            "expected a multiple of 90 degrees",
+        ));
+    }
+
+    let instruction = match direction {
+        'N' => Instruction::N(parsed_val),
+        'S' => Instruction::S(parsed_val),
+        'E' => Instruction::E(parsed_val),
+        'W' => Instruction::W(parsed_val),
+        'L' => Instruction::L(parsed_val as usize),
+        'R' => Instruction::R(parsed_val as usize),
+        'F' => Instruction::F(parsed_val as usize),
+        _ => {
+            return Err(AocError::parse(
+                DAY,
+                idx + 1,
+                1,
+                &direction.to_string(),
+                "expected one of `NSEWLRF`",
+            ))
+        }
+    };
 
-    Ok(instructions)
+    Ok(instruction)
 }
 
+/// Manhattan distance travelled moving the ferry directly.
 #[aoc(day12, part1)]
-fn part_1(instructions: &[Instruction]) -> usize {
+pub fn part_1(instructions: &[Instruction]) -> usize {
     let mut ferry = Ferry::new();
     for inst in instructions {
         ferry.apply_move(inst);
     }
 
-    (ferry.w.abs() + ferry.n.abs()) as usize
+    ferry.distance()
 }
 
+/// Manhattan distance travelled moving the ferry towards the waypoint.
 #[aoc(day12, part2)]
-fn part_2(instructions: &[Instruction]) -> usize {
+pub fn part_2(instructions: &[Instruction]) -> usize {
     let mut ferry = Ferry::new();
     let mut waypoint = Waypoint::new();
 
     for inst in instructions {
-        match inst {
-            Instruction::E(v) => waypoint.apply_move(&Instruction::E(*v)),
-            Instruction::N(v) => waypoint.apply_move(&Instruction::N(*v)),
-            Instruction::S(v) => waypoint.apply_move(&Instruction::S(*v)),
-            Instruction::W(v) => waypoint.apply_move(&Instruction::W(*v)),
-            Instruction::R(v) => waypoint.apply_move(&Instruction::R(*v)),
-            Instruction::L(v) => waypoint.apply_move(&Instruction::L(*v)),
-            Instruction::F(v) => {
-                let (x, y) = waypoint.get_rel_pos();
-                let actual_x_move = match x {
-                    Instruction::E(w) => Instruction::E(w * (*v as isize)),
-                    Instruction::W(w) => Instruction::W(w * (*v as isize)),
-                    _ => unreachable!(),
-                };
-
-                let actual_y_move = match y {
-                    Instruction::N(w) => Instruction::N(w * (*v as isize)),
-                    Instruction::S(w) => Instruction::S(w * (*v as isize)),
-                    _ => unreachable!(),
-                };
-                ferry.apply_move(&actual_x_move);
-                ferry.apply_move(&actual_y_move);
-            }
-        }
+        steer(&mut ferry, &mut waypoint, inst);
     }
 
-    (ferry.w.abs() + ferry.n.abs()) as usize
+    ferry.distance()
+}
+
+/// Moves the waypoint, or the ferry towards it.
+fn steer(ferry: &mut Ferry, waypoint: &mut Waypoint, inst: &Instruction) {
+    match inst {
+        Instruction::E(v) => waypoint.apply_move(&Instruction::E(*v)),
+        Instruction::N(v) => waypoint.apply_move(&Instruction::N(*v)),
+        Instruction::S(v) => waypoint.apply_move(&Instruction::S(*v)),
+        Instruction::W(v) => waypoint.apply_move(&Instruction::W(*v)),
+        Instruction::R(v) => waypoint.apply_move(&Instruction::R(*v)),
+        Instruction::L(v) => waypoint.apply_move(&Instruction::L(*v)),
+        Instruction::F(v) => {
+            let (x, y) = waypoint.get_rel_pos();
+            let actual_x_move = match x {
+                Instruction::E(w) => Instruction::E(w * (*v as isize)),
+                Instruction::W(w) => Instruction::W(w * (*v as isize)),
+                _ => unreachable!(),
+            };
+
+            let actual_y_move = match y {
+                Instruction::N(w) => Instruction::N(w * (*v as isize)),
+                Instruction::S(w) => Instruction::S(w * (*v as isize)),
+                _ => unreachable!(),
+            };
+            ferry.apply_move(&actual_x_move);
+            ferry.apply_move(&actual_y_move);
+        }
+    }
 }
 
 struct Waypoint {
@@ -202,6 +249,11 @@ impl Ferry {
         };
     }
 
+    /// Manhattan distance from the starting point.
+    fn distance(&self) -> usize {
+        (self.w.abs() + self.n.abs()) as usize
+    }
+
     fn move_forward(&mut self, val: usize) {
         let direction = self.facing;
         match direction {
@@ -229,8 +281,9 @@ enum Direction {
     W,
 }
 
+/// Navigation instruction, also used as a relative displacement.
 #[derive(Debug, PartialEq)]
-enum Instruction {
+pub enum Instruction {
     N(isize),
     S(isize),
     E(isize),
@@ -240,6 +293,47 @@ enum Instruction {
     F(usize),
 }
 
+/// Solver for day 12, usable outside of cargo-aoc.
+pub struct Day12;
+
+impl Solver for Day12 {
+    type Input = Vec<Instruction>;
+    type Output = usize;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        parse_instructions(input)
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(part_1(input))
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(part_2(input))
+    }
+}
+
+impl StreamSolver for Day12 {
+    fn part1_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        let mut ferry = Ferry::new();
+        for inst in read_instructions(reader) {
+            ferry.apply_move(&inst?);
+        }
+
+        Ok(ferry.distance())
+    }
+
+    fn part2_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        let mut ferry = Ferry::new();
+        let mut waypoint = Waypoint::new();
+        for inst in read_instructions(reader) {
+            steer(&mut ferry, &mut waypoint, &inst?);
+        }
+
+        Ok(ferry.distance())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -259,8 +353,10 @@ mod tests {
         let mut ferry = Ferry::new();
         ferry.rotate(&Instruction::R(90));
         assert_eq!(ferry.facing, Direction::S);
-        // ferry.rotate(&Instruction::L(90));
-        // assert_eq!(ferry.facing, Direction::N);
+        ferry.rotate(&Instruction::L(90));
+        assert_eq!(ferry.facing, Direction::E);
+        ferry.rotate(&Instruction::L(180));
+        assert_eq!(ferry.facing, Direction::W);
     }
 
     #[test]
@@ -301,4 +397,24 @@ mod tests {
         assert_eq!(waypoint.n, -1);
         // assert!(false);
     }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_instructions("F10\nX3").err(),
+            Some(AocError::parse(12, 2, 1, "X", "expected one of `NSEWLRF`"))
+        );
+        assert_eq!(
+            parse_instructions("F10\nN3\nR45").err(),
+            Some(AocError::parse(
+                12,
+                3,
+                2,
+                "45",
+                "expected a multiple of 90 degrees"
+            ))
+        );
+        assert!(parse_instructions("F10\n\nN3").is_err());
+        assert!(parse_instructions("Fx").is_err());
+    }
 }
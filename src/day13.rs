@@ -1,26 +1,27 @@
+use crate::error::{column, parse_token};
+use crate::{AocError, Solver};
 use aoc_runner_derive::{aoc, aoc_generator};
 
+const DAY: u32 = 13;
+
+/// Parses the earliest departure time and the bus ids in service.
 #[aoc_generator(day13, part1)]
-fn parse_input(input: &str) -> (i128, Vec<i128>) {
+pub fn parse_input(input: &str) -> Result<(i128, Vec<i128>), AocError> {
     let mut lines = input.lines();
-    let current_time = lines.next().unwrap().parse().unwrap();
-    let values = lines.next().unwrap().split(",");
+    let time_line = lines.next().unwrap_or("");
+    let current_time = parse_token(DAY, 0, time_line, time_line)?;
 
-    let mut parsed_values = vec![];
+    let parsed_values = parse_input_p2(input)?
+        .into_iter()
+        .filter(|v| *v != -1)
+        .collect();
 
-    for v in values {
-        if v == "x" {
-            continue;
-        } else {
-            parsed_values.push(v.parse().unwrap());
-        }
-    }
-
-    (current_time, parsed_values)
+    Ok((current_time, parsed_values))
 }
 
+/// Id of the earliest bus times the minutes waited for it.
 #[aoc(day13, part1)]
-fn part_1(input: &(i128, Vec<i128>)) -> i128 {
+pub fn part_1(input: &(i128, Vec<i128>)) -> Result<i128, AocError> {
     let (current_time, mut values) = input.clone();
     let mut dist_vec = vec![];
     let mut i = current_time;
@@ -42,98 +43,207 @@ fn part_1(input: &(i128, Vec<i128>)) -> i128 {
 
     dist_vec.sort_by(|a, b| a.2.cmp(&b.2));
 
-    let (id, time, dist) = dist_vec[0];
-
-    return id * dist;
+    match dist_vec.first() {
+        Some((id, _time, dist)) => Ok(id * dist),
+        None => Err(AocError::no_solution(DAY, "there are no buses in service")),
+    }
 }
 
+/// Parses the bus ids keeping their offsets, `x` becoming `-1`.
 #[aoc_generator(day13, part2)]
-fn parse_input_p2(input: &str) -> Vec<i128> {
+pub fn parse_input_p2(input: &str) -> Result<Vec<i128>, AocError> {
     let mut parsed_values = vec![];
 
     let mut lines = input.lines();
     lines.next();
-    let values = lines.next().unwrap().split(",");
-    for v in values {
+    let line = match lines.next() {
+        Some(l) => l,
+        None => {
+            return Err(AocError::parse(
+                DAY,
+                2,
+                1,
+                "",
+                "expected the list of bus ids",
+            ));
+        }
+    };
+    for v in line.split(",") {
         if v == "x" {
             parsed_values.push(-1);
         } else {
-            parsed_values.push(v.parse().unwrap());
+            let id = parse_token(DAY, 1, line, v)?;
+            if id <= 0 {
+                return Err(AocError::parse(
+                    DAY,
+                    2,
+                    column(line, v),
+                    v,
+                    "bus ids must be positive",
+                ));
+            }
+            parsed_values.push(id);
         }
     }
 
-    return parsed_values;
+    Ok(parsed_values)
 }
 
+/// Earliest timestamp at which every bus departs at its offset.
 #[aoc(day13, part2)]
-fn part_2(input: &[i128]) -> i128 {
-    let mut i = 1;
-    let mut found = false;
-    let values = input.to_vec();
-
-    loop {
-        let mut values_iter = values.iter();
-        let mut k = i;
-        // println!("{}", i);
-        loop {
-            if let Some(x) = values_iter.next() {
-                // print!("({},{})  ", x, k);
-                if *x == -1 {
-                    k += 1;
-                    continue;
-                } else if !(k % x == 0) {
-                    // println!("{}", !x);
-                    break;
-                }
-            } else {
-                found = true;
-                break;
+pub fn part_2(input: &[i128]) -> Result<i128, AocError> {
+    // Sieve: once a timestamp fits the first buses, adding the lcm of their
+    // ids keeps fitting them while looking for the next one
+    let mut timestamp = 0;
+    let mut step = 1;
+    for (offset, id) in input.iter().enumerate().filter(|(_, id)| **id != -1) {
+        let offset = offset as i128;
+        // The remainders repeat after `id` steps
+        let fits = (0..*id).find(|k| (timestamp + k * step + offset) % id == 0);
+        match fits {
+            Some(k) => timestamp += k * step,
+            None => {
+                return Err(AocError::no_solution(
+                    DAY,
+                    format!("bus {} never departs at offset {}", id, offset),
+                ))
             }
-
-            k += 1;
         }
-        if found {
-            break;
-        }
-        i += 1;
-        // println!("");
-        // println!("");
+        step = lcm(step, *id);
+    }
+
+    // Timestamps start at 1
+    Ok(if timestamp == 0 { step } else { timestamp })
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn lcm(a: i128, b: i128) -> i128 {
+    a / gcd(a, b) * b
+}
+
+/// Solver for day 13, usable outside of cargo-aoc.
+pub struct Day13;
+
+impl Solver for Day13 {
+    type Input = String;
+    type Output = i128;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        // Both generators share the same format, check it once up front
+        parse_input(input)?;
+        Ok(input.to_string())
     }
 
-    return i;
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        part_1(&parse_input(input)?)
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        part_2(&parse_input_p2(input)?)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     const EXAMPLE_INPUT: &str = "939\n7,13,x,x,59,x,31,19";
 
     #[test]
     fn test_parse_input() {
-        let (time, values) = parse_input(EXAMPLE_INPUT);
+        let (time, values) = parse_input(EXAMPLE_INPUT).unwrap();
         assert_eq!(time, 939);
         assert_eq!(values, vec![7, 13, 59, 31, 19]);
     }
 
     #[test]
     fn test_p1() {
-        let input = parse_input(EXAMPLE_INPUT);
-        let p1 = part_1(&input);
+        let input = parse_input(EXAMPLE_INPUT).unwrap();
+        let p1 = part_1(&input).unwrap();
         assert_eq!(295, p1);
     }
 
     #[test]
     fn test_input_2() {
-        let values = parse_input_p2(EXAMPLE_INPUT);
+        let values = parse_input_p2(EXAMPLE_INPUT).unwrap();
         assert_eq!(vec![7, 13, -1, -1, 59, -1, 31, 19], values);
     }
 
     #[test]
     fn test_p2() {
-        let values = parse_input_p2(EXAMPLE_INPUT);
-        assert_eq!(part_2(&values), 1068781);
-        let values2 = parse_input_p2("XX\n67,x,7,59,61");
-        assert_eq!(part_2(&values2), 779210);
+        let values = parse_input_p2(EXAMPLE_INPUT).unwrap();
+        assert_eq!(part_2(&values), Ok(1068781));
+        let values2 = parse_input_p2("XX\n67,x,7,59,61").unwrap();
+        assert_eq!(part_2(&values2), Ok(779210));
+        assert_eq!(part_2(&[3, 5]), Ok(9));
+        assert!(part_2(&[2, 4]).is_err());
+    }
+
+    /// The original solution, trying every timestamp in turn.
+    fn reference_part_2(input: &[i128]) -> i128 {
+        let mut i = 1;
+        loop {
+            let fits = input
+                .iter()
+                .enumerate()
+                .all(|(offset, id)| *id == -1 || (i + offset as i128) % id == 0);
+            if fits {
+                return i;
+            }
+            i += 1;
+        }
+    }
+
+    fn buses() -> impl Strategy<Value = Vec<i128>> {
+        // Distinct primes, so a timestamp always exists, small enough for
+        // the reference to find it quickly
+        let primes = vec![2i128, 3, 5, 7, 11, 13, 17, 19, 23];
+        (
+            proptest::sample::subsequence(primes, 1..=4).prop_shuffle(),
+            proptest::collection::vec(0..3usize, 4),
+        )
+            .prop_map(|(ids, gaps)| {
+                let mut buses = vec![];
+                for (id, gap) in ids.into_iter().zip(gaps) {
+                    buses.push(id);
+                    buses.extend(std::iter::repeat_n(-1, gap));
+                }
+                buses
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn test_p2_matches_reference(buses in buses()) {
+            prop_assert_eq!(part_2(&buses), Ok(reference_part_2(&buses)));
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_input("939\n7,13,y,59").err(),
+            Some(AocError::parse(
+                13,
+                2,
+                6,
+                "y",
+                "invalid digit found in string"
+            ))
+        );
+        assert_eq!(
+            parse_input("939\n7,0").err(),
+            Some(AocError::parse(13, 2, 3, "0", "bus ids must be positive"))
+        );
+        assert!(parse_input("939").is_err());
+        assert!(parse_input("soon\n7,13").is_err());
     }
 }
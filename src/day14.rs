@@ -1,70 +1,123 @@
-use aoc_runner_derive::{aoc, aoc_generator};
+use crate::error::{column, parse_token};
+use crate::stream::records;
+use crate::{AocError, Solver, StreamSolver};
+use aoc_runner_derive::aoc;
 use itertools::Itertools;
+use lazy_static::lazy_static;
 use regex::Regex;
 use std::collections::HashMap;
+use std::io::BufRead;
 
-#[aoc(day14, part1)]
-fn part_1(input: &str) -> u64 {
-    let mask_matcher = Regex::new(r"mask\s*=\s*(?P<mask>.+)").unwrap();
-    let mem_matcher = Regex::new(r"mem\[(?P<addr>\d+)\] = (?P<value>\d+)").unwrap();
-
-    let mut memory = HashMap::<u64, u64>::new();
-
-    let mut mask = "";
-    for line in input.lines() {
-        match mask_matcher.captures(line) {
-            Some(caps) => {
-                mask = caps.name("mask").unwrap();
-            }
-            None => (),
-        }
+const DAY: u32 = 14;
 
-        match mem_matcher.captures(line) {
-            Some(caps) => {
-                let address = caps.name("addr").unwrap().parse().unwrap();
-                let value = caps.name("value").unwrap().parse().unwrap();
-                memory.insert(address, apply_mask(value, mask));
-            }
-            None => (),
+enum Command<'a> {
+    Mask(&'a str),
+    Mem(u64, u64),
+}
+
+/// Parses the `idx`-th (0-based) line of the program.
+fn parse_command(idx: usize, line: &str) -> Result<Command<'_>, AocError> {
+    lazy_static! {
+        static ref MASK_MATCHER: Regex = Regex::new(r"^mask\s*=\s*(?P<mask>.+)$").unwrap();
+        static ref MEM_MATCHER: Regex =
+            Regex::new(r"^mem\[(?P<addr>\d+)\] = (?P<value>\d+)$").unwrap();
+    }
+
+    if let Some(caps) = MASK_MATCHER.captures(line) {
+        let mask = caps.name("mask").unwrap();
+        if mask.len() != 36 || mask.chars().any(|c| !"01X".contains(c)) {
+            return Err(AocError::parse(
+                DAY,
+                idx + 1,
+                column(line, mask),
+                mask,
+                "expected 36 bits of `0`, `1` or `X`",
+            ));
+        }
+        Ok(Command::Mask(mask))
+    } else if let Some(caps) = MEM_MATCHER.captures(line) {
+        let addr = caps.name("addr").unwrap();
+        let address: u64 = parse_token(DAY, idx, line, addr)?;
+        if address >= 1 << 36 {
+            return Err(AocError::parse(
+                DAY,
+                idx + 1,
+                column(line, addr),
+                addr,
+                "addresses are 36 bits wide",
+            ));
         }
+        let value = parse_token(DAY, idx, line, caps.name("value").unwrap())?;
+        Ok(Command::Mem(address, value))
+    } else {
+        Err(AocError::parse(
+            DAY,
+            idx + 1,
+            1,
+            line,
+            "expected `mask = ...` or `mem[address] = value`",
+        ))
     }
+}
+
+fn mask_not_set(idx: usize, line: &str) -> AocError {
+    AocError::parse(
+        DAY,
+        idx + 1,
+        1,
+        line,
+        "memory written before setting a mask",
+    )
+}
 
-    memory.iter().fold(0u64, |acc, (_k, v)| acc + v)
+/// Sum of the memory after masking the written values.
+#[aoc(day14, part1)]
+pub fn part_1(input: &str) -> Result<u64, AocError> {
+    run_program(input.as_bytes(), write_masked_value)
 }
 
+/// Sum of the memory after masking the addresses written to.
 #[aoc(day14, part2)]
-fn part_2(input: &str) -> u64 {
-    let mask_matcher = Regex::new(r"mask\s*=\s*(?P<mask>.+)").unwrap();
-    let mem_matcher = Regex::new(r"mem\[(?P<addr>\d+)\] = (?P<value>\d+)").unwrap();
-
-    let mut memory = HashMap::<u64, u64>::new();
-
-    let mut mask = "";
-    for line in input.lines() {
-        match mask_matcher.captures(line) {
-            Some(caps) => {
-                mask = caps.name("mask").unwrap();
-            }
-            None => (),
-        }
+pub fn part_2(input: &str) -> Result<u64, AocError> {
+    run_program(input.as_bytes(), write_masked_addresses)
+}
+
+type Memory = HashMap<u64, u64>;
+
+fn write_masked_value(memory: &mut Memory, mask: &str, address: u64, value: u64) {
+    memory.insert(address, apply_mask(value, mask));
+}
+
+fn write_masked_addresses(memory: &mut Memory, mask: &str, address: u64, value: u64) {
+    let address_str = format!("{:036b}", address);
+    let masked_address = mask_addr(&address_str, mask);
+    let expanded_addresses = expand_address(&masked_address);
+    for addr in expanded_addresses {
+        memory.insert(addr, value);
+    }
+}
 
-        match mem_matcher.captures(line) {
-            Some(caps) => {
-                let address: u64 = caps.name("addr").unwrap().parse().unwrap();
-                let value = caps.name("value").unwrap().parse().unwrap();
-
-                let address_str = format!("{:036b}", address);
-                let masked_address = mask_addr(&address_str, mask);
-                let expanded_addresses = expand_address(&masked_address);
-                for addr in expanded_addresses {
-                    memory.insert(addr, value);
-                }
-            }
-            None => (),
+/// Runs the program one line at a time out of `reader`, storing values with
+/// `write`, and sums the memory. Only the memory written to is kept around.
+fn run_program<R: BufRead>(
+    reader: R,
+    write: fn(&mut Memory, &str, u64, u64),
+) -> Result<u64, AocError> {
+    let mut memory = Memory::new();
+
+    let mut mask = String::new();
+    for result in records(reader, |idx, line| {
+        match parse_command(idx, line)? {
+            Command::Mask(m) => mask = m.to_string(),
+            Command::Mem(_, _) if mask.is_empty() => return Err(mask_not_set(idx, line)),
+            Command::Mem(address, value) => write(&mut memory, &mask, address, value),
         }
+        Ok(())
+    }) {
+        result?;
     }
 
-    memory.iter().fold(0u64, |acc, (_k, v)| acc + v)
+    Ok(memory.iter().fold(0u64, |acc, (_k, v)| acc + v))
 }
 
 fn mask_addr(addr: &str, mask: &str) -> String {
@@ -157,6 +210,36 @@ fn apply_mask(value: u64, mask: &str) -> u64 {
     return modified;
 }
 
+/// Solver for day 14, usable outside of cargo-aoc.
+pub struct Day14;
+
+impl Solver for Day14 {
+    type Input = String;
+    type Output = u64;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        Ok(input.to_string())
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        part_1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        part_2(input)
+    }
+}
+
+impl StreamSolver for Day14 {
+    fn part1_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        run_program(reader, write_masked_value)
+    }
+
+    fn part2_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError> {
+        run_program(reader, write_masked_addresses)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -184,17 +267,43 @@ mod tests {
         let mem_matcher = Regex::new(r"mem\[(?P<addr>\d+)\] = (?P<value>\d+)").unwrap();
         assert!(mask_matcher.is_match("mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X"));
         assert!(mem_matcher.is_match("mem[8] = 11"));
-        let count = part_1(EXAMPLE_INPUT);
+        let count = part_1(EXAMPLE_INPUT).unwrap();
         assert_eq!(count, 165);
     }
 
     #[test]
     fn test_p2() {
         let input = "mask = 000000000000000000000000000000X1001X\nmem[42] = 100\nmask = 00000000000000000000000000000000X0XX\nmem[26] = 1";
-        let count = part_2(input);
+        let count = part_2(input).unwrap();
         assert_eq!(count, 208);
     }
 
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            part_1("mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X\nmem[8] = 1 1").err(),
+            Some(AocError::parse(
+                14,
+                2,
+                1,
+                "mem[8] = 1 1",
+                "expected `mask = ...` or `mem[address] = value`"
+            ))
+        );
+        assert_eq!(
+            part_1("mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX2X").err(),
+            Some(AocError::parse(
+                14,
+                1,
+                8,
+                "XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX2X",
+                "expected 36 bits of `0`, `1` or `X`"
+            ))
+        );
+        assert!(part_2("mem[8] = 11").is_err());
+        assert!(part_2("mask = 0X\nmem[8] = 11").is_err());
+    }
+
     #[test]
     fn test_expand_addr() {
         let addr = "10XX01";
@@ -1,18 +1,30 @@
+use crate::error::parse_token;
+use crate::{AocError, Solver};
 use aoc_runner_derive::aoc;
 use std::collections::HashMap;
 
+const DAY: u32 = 15;
+
+/// 2020th number spoken in the memory game.
 #[aoc(day15, part1)]
-fn part_1(input: &str) -> usize {
+pub fn part_1(input: &str) -> Result<usize, AocError> {
     return get_nth(input, 2020);
 }
 
+/// 30000000th number spoken in the memory game.
 #[aoc(day15, part2)]
-fn part_2(input: &str) -> usize {
+pub fn part_2(input: &str) -> Result<usize, AocError> {
     return get_nth(input, 30000000);
 }
 
-fn get_nth(input: &str, n: usize) -> usize {
-    let mut seeds: Vec<usize> = input.split(",").map(|x| x.parse().unwrap()).collect();
+fn get_nth(input: &str, n: usize) -> Result<usize, AocError> {
+    if n == 0 {
+        return Err(AocError::invalid_argument("turns start at 1"));
+    }
+    let mut seeds: Vec<usize> = input
+        .split(",")
+        .map(|x| parse_token(DAY, 0, input, x))
+        .collect::<Result<_, AocError>>()?;
 
     let mut position_hash = HashMap::<usize, usize>::new();
 
@@ -37,7 +49,27 @@ fn get_nth(input: &str, n: usize) -> usize {
         seeds.push(new);
     }
 
-    seeds[n - 1]
+    Ok(seeds[n - 1])
+}
+
+/// Solver for day 15, usable outside of cargo-aoc.
+pub struct Day15;
+
+impl Solver for Day15 {
+    type Input = String;
+    type Output = usize;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        Ok(input.to_string())
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        part_1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        part_2(input)
+    }
 }
 
 #[cfg(test)]
@@ -45,7 +77,22 @@ mod tests {
     use super::*;
     #[test]
     fn test_p1() {
-        let val = part_1("0,3,6");
+        let val = part_1("0,3,6").unwrap();
         assert_eq!(val, 436);
     }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            part_1("0,3,,6").err(),
+            Some(AocError::parse(
+                15,
+                1,
+                5,
+                "",
+                "cannot parse integer from empty string"
+            ))
+        );
+        assert!(get_nth("0,3,6", 0).is_err());
+    }
 }
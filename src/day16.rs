@@ -1,60 +1,85 @@
+use crate::error::parse_token;
+use crate::{AocError, Solver};
 use aoc_runner_derive::{aoc, aoc_generator};
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
 
-struct Input {
+const DAY: u32 = 16;
+
+/// Ticket field rules, your ticket and the nearby tickets.
+pub struct Input {
     hash: HashMap<String, [usize; 4]>,
     my_ticket: Vec<usize>,
     nearby_tickets: Vec<Vec<usize>>,
 }
 
+/// Parses the ticket field rules, your ticket and the nearby tickets.
 #[aoc_generator(day16)]
-fn parse_input(input: &str) -> Input {
-    const MY_TICKET_IDX: usize = 22;
-    const NEARBY_TICKETS_START_IDX: usize = 25;
-
+pub fn parse_input(input: &str) -> Result<Input, AocError> {
     let mut values_range = HashMap::<String, [usize; 4]>::new();
     let mut nearby_tickets = vec![];
 
     let range_matcher =
-        Regex::new(r"(?P<key>.+): (?P<v1>\d+)-(?P<v2>\d+) or (?P<v3>\d+)-(?P<v4>\d+)").unwrap();
+        Regex::new(r"^(?P<key>.+): (?P<v1>\d+)-(?P<v2>\d+) or (?P<v3>\d+)-(?P<v4>\d+)$").unwrap();
 
     let lines: Vec<&str> = input.lines().collect();
+    let section_start = |header: &str| {
+        lines
+            .iter()
+            .position(|l| l.trim() == header)
+            .ok_or_else(|| AocError::parse(DAY, lines.len().max(1), 1, header, "missing section"))
+    };
+    let my_ticket_idx = section_start("your ticket:")? + 1;
+    let nearby_tickets_start_idx = section_start("nearby tickets:")? + 1;
 
-    for i in 0..MY_TICKET_IDX {
-        let current_line = lines[i];
+    for (i, current_line) in lines.iter().enumerate().take(my_ticket_idx - 1) {
+        if current_line.trim().is_empty() {
+            continue;
+        }
         match range_matcher.captures(current_line) {
             Some(captures) => {
                 let key = captures.name("key").unwrap().to_string();
-                let v1 = captures.name("v1").unwrap().parse().unwrap();
-                let v2 = captures.name("v2").unwrap().parse().unwrap();
-                let v3 = captures.name("v3").unwrap().parse().unwrap();
-                let v4 = captures.name("v4").unwrap().parse().unwrap();
-                values_range.insert(key, [v1, v2, v3, v4]);
+                let mut range = [0; 4];
+                for (v, name) in range.iter_mut().zip(["v1", "v2", "v3", "v4"].iter()) {
+                    *v = parse_token(DAY, i, current_line, captures.name(name).unwrap())?;
+                }
+                values_range.insert(key, range);
+            }
+            None => {
+                return Err(AocError::parse(
+                    DAY,
+                    i + 1,
+                    1,
+                    current_line,
+                    "expected a rule like `class: 1-3 or 5-7`",
+                ))
             }
-            None => (),
         }
     }
 
-    let my_ticket = lines[MY_TICKET_IDX]
-        .split(",")
-        .map(|v| v.parse().unwrap())
-        .collect();
+    let parse_ticket = |i: usize| -> Result<Vec<usize>, AocError> {
+        let line = lines.get(i).copied().unwrap_or("");
+        line.split(",")
+            .map(|v| parse_token(DAY, i, line, v))
+            .collect()
+    };
+
+    let my_ticket = parse_ticket(my_ticket_idx)?;
 
-    for i in NEARBY_TICKETS_START_IDX..lines.len() {
-        let current_row = lines[i].split(",").map(|v| v.parse().unwrap()).collect();
-        nearby_tickets.push(current_row);
+    for i in nearby_tickets_start_idx..lines.len() {
+        nearby_tickets.push(parse_ticket(i)?);
     }
 
-    Input {
+    Ok(Input {
         hash: values_range,
         my_ticket,
         nearby_tickets,
-    }
+    })
 }
 
+/// Sum of the values of nearby tickets that are not valid for any field.
 #[aoc(day16, part1)]
-fn part_1(in_values: &Input) -> usize {
+pub fn part_1(in_values: &Input) -> usize {
     let mut err_count = 0;
 
     let in_range = |x: usize, v: &[usize; 4]| (x >= v[0] && x <= v[1]) || (x >= v[2] && x <= v[3]);
@@ -84,8 +109,9 @@ fn part_1(in_values: &Input) -> usize {
     return err_count;
 }
 
+/// Product of the departure fields of your ticket.
 #[aoc(day16, part2)]
-fn part_2(in_values: &Input) -> usize {
+pub fn part_2(in_values: &Input) -> Result<usize, AocError> {
     let in_range = |x: usize, v: &[usize; 4]| (x >= v[0] && x <= v[1]) || (x >= v[2] && x <= v[3]);
 
     let nearby_tickets = &in_values.nearby_tickets;
@@ -125,7 +151,10 @@ fn part_2(in_values: &Input) -> usize {
         valid_tickets.push(nearby_tickets[idx].to_owned());
     }
 
-    let columns = valid_tickets[0].len();
+    let columns = match valid_tickets.first() {
+        Some(ticket) => ticket.len(),
+        None => return Err(AocError::no_solution(DAY, "every nearby ticket is invalid")),
+    };
     let rows = valid_tickets.len();
 
     let column_valid_in_range = |column_idx: usize, range: &[usize; 4]| {
@@ -178,20 +207,107 @@ fn part_2(in_values: &Input) -> usize {
             }
         }
 
+        if rm_key.is_empty() {
+            return Err(AocError::no_solution(
+                DAY,
+                "the remaining fields cannot be told apart",
+            ));
+        }
+
         field_set.remove(rm_key);
         column_set.remove(&rm_column);
     }
 
-    let c1 = field_column_hash.get("departure location").unwrap();
-    let c2 = field_column_hash.get("departure station").unwrap();
-    let c3 = field_column_hash.get("departure platform").unwrap();
-    let c4 = field_column_hash.get("departure track").unwrap();
-    let c5 = field_column_hash.get("departure date").unwrap();
-    let c6 = field_column_hash.get("departure time").unwrap();
+    let departure_fields = [
+        "departure location",
+        "departure station",
+        "departure platform",
+        "departure track",
+        "departure date",
+        "departure time",
+    ];
+
+    let mut product = 1;
+    for field in departure_fields.iter() {
+        let value = field_column_hash
+            .get(field)
+            .and_then(|c| my_ticket.get(*c))
+            .ok_or_else(|| AocError::no_solution(DAY, format!("there is no {} field", field)))?;
+        product *= value;
+    }
+
+    Ok(product)
+}
+
+/// Solver for day 16, usable outside of cargo-aoc.
+pub struct Day16;
+
+impl Solver for Day16 {
+    type Input = Input;
+    type Output = usize;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        parse_input(input)
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(part_1(input))
+    }
 
-    let columns_departure = [c1, c2, c3, c4, c5, c6];
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError> {
+        part_2(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_INPUT: &str = "class: 1-3 or 5-7
+row: 6-11 or 33-44
+seat: 13-40 or 45-50
+
+your ticket:
+7,1,14
+
+nearby tickets:
+7,3,47
+40,4,50
+55,2,20
+38,6,12";
+
+    #[test]
+    fn test_parse_input() {
+        let input = parse_input(EXAMPLE_INPUT).unwrap();
+        assert_eq!(input.hash.len(), 3);
+        assert_eq!(input.hash["row"], [6, 11, 33, 44]);
+        assert_eq!(input.my_ticket, vec![7, 1, 14]);
+        assert_eq!(input.nearby_tickets.len(), 4);
+    }
 
-    columns_departure
-        .iter()
-        .fold(1, |acc, c| acc * my_ticket[**c])
+    #[test]
+    fn test_p1() {
+        let input = parse_input(EXAMPLE_INPUT).unwrap();
+        assert_eq!(part_1(&input), 71);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let input = EXAMPLE_INPUT.replace("40,4,50", "40,4,5O");
+        assert_eq!(
+            parse_input(&input).err(),
+            Some(AocError::parse(
+                16,
+                10,
+                6,
+                "5O",
+                "invalid digit found in string"
+            ))
+        );
+        let input = EXAMPLE_INPUT.replace("row: 6-11", "row 6-11");
+        assert!(parse_input(&input).is_err());
+        assert!(parse_input("class: 1-3 or 5-7").is_err());
+        let input = parse_input(EXAMPLE_INPUT).unwrap();
+        assert!(part_2(&input).is_err());
+    }
 }
@@ -1,9 +1,13 @@
+use crate::{AocError, Solver};
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::collections::HashMap;
 use std::fmt;
 
+const DAY: u32 = 17;
+
+/// Fixed size cube of the pocket dimension.
 #[derive(Clone)]
-struct Space {
+pub struct Space {
     points: Vec<Vec<Vec<char>>>,
     len: usize,
 }
@@ -142,20 +146,36 @@ impl Space {
     }
 }
 
+/// Places the initial slice of cubes at `z = 0`.
 #[aoc_generator(day17)]
-fn parse_input(input: &str) -> Space {
+pub fn parse_input(input: &str) -> Result<Space, AocError> {
     let mut space = Space::new(100);
-    input.lines().enumerate().for_each(|(y, l)| {
-        l.chars().enumerate().for_each(|(x, c)| {
+    for (y, l) in input.lines().enumerate() {
+        for (x, c) in l.chars().enumerate() {
+            if c != '.' && c != '#' {
+                let text = c.to_string();
+                return Err(AocError::parse(
+                    DAY,
+                    y + 1,
+                    x + 1,
+                    &text,
+                    "expected `.` or `#`",
+                ));
+            }
+            if x >= space.len || y >= space.len {
+                let reason = format!("the slice does not fit in {} cubes", space.len);
+                return Err(AocError::parse(DAY, y + 1, x + 1, l, reason));
+            }
             space.set_point((x, y, 0), c);
-        })
-    });
+        }
+    }
 
-    return space;
+    Ok(space)
 }
 
+/// Active cubes after six cycles.
 #[aoc(day17, part1)]
-fn part_1_17(input: &Space) -> usize {
+pub fn part_1_17(input: &Space) -> usize {
     let mut space = input.clone();
 
     for i in 0..6 {
@@ -174,13 +194,33 @@ fn part_1_17(input: &Space) -> usize {
     space.count_active()
 }
 
+/// Solver for day 17, usable outside of cargo-aoc.
+pub struct Day17;
+
+impl Solver for Day17 {
+    type Input = Space;
+    type Output = usize;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError> {
+        parse_input(input)
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError> {
+        Ok(part_1_17(input))
+    }
+
+    fn part2(_input: &Self::Input) -> Result<Self::Output, AocError> {
+        Err(AocError::no_solution(DAY, "part 2 is not solved yet"))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     #[test]
     fn test_parse_input() {
         let input = ".#.\n..#\n###";
-        let mut space = parse_input(input);
+        let mut space = parse_input(input).unwrap();
         // dbg!(space.points);
         let count = part_1_17(&space);
         assert_eq!(space.count_active_neighbours((1, 1, 0)), 6);
@@ -189,7 +229,7 @@ mod test {
     #[test]
     fn test_cycle() {
         let input = ".#.\n..#\n###";
-        let mut space = parse_input(input);
+        let mut space = parse_input(input).unwrap();
         assert_eq!(space.count_active(), 5);
         space.cycle();
         // assert_eq!(space.count_active_neighbours(()), 11);
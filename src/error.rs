@@ -0,0 +1,171 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io;
+use std::str::FromStr;
+
+/// Error returned by every generator and part of the calendar.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AocError {
+    /// The puzzle input is malformed. `line` and `column` are 1-based and
+    /// `text` is the offending piece of input.
+    Parse {
+        day: u32,
+        line: usize,
+        column: usize,
+        text: String,
+        reason: String,
+    },
+    /// The input is well formed but the puzzle has no answer for it.
+    NoSolution { day: u32, reason: String },
+    /// An intermediate result doesn't fit in the number type being used.
+    Overflow { day: u32, reason: String },
+    /// A day, part or parameter outside of what the solvers support.
+    InvalidArgument(String),
+    /// The input could not be read, or isn't valid UTF-8.
+    Io(String),
+}
+
+impl AocError {
+    pub fn parse(day: u32, line: usize, column: usize, text: &str, reason: impl Display) -> Self {
+        AocError::Parse {
+            day,
+            line,
+            column,
+            text: text.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn no_solution(day: u32, reason: impl Display) -> Self {
+        AocError::NoSolution {
+            day,
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn overflow(day: u32, reason: impl Display) -> Self {
+        AocError::Overflow {
+            day,
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn invalid_argument(reason: impl Display) -> Self {
+        AocError::InvalidArgument(reason.to_string())
+    }
+
+    /// Shifts a parse error reported relative to a single record (whose
+    /// first line is line 1) to the position of the record in the input.
+    pub fn offset_lines(self, lines: usize) -> Self {
+        match self {
+            AocError::Parse {
+                day,
+                line,
+                column,
+                text,
+                reason,
+            } => AocError::Parse {
+                day,
+                line: line + lines,
+                column,
+                text,
+                reason,
+            },
+            other => other,
+        }
+    }
+}
+
+impl Display for AocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AocError::Parse {
+                day,
+                line,
+                column,
+                text,
+                reason,
+            } => write!(
+                f,
+                "day {}, line {}, column {}: {}: {:?}",
+                day, line, column, reason, text
+            ),
+            AocError::NoSolution { day, reason } => {
+                write!(f, "day {}: no solution: {}", day, reason)
+            }
+            AocError::Overflow { day, reason } => write!(f, "day {}: overflow: {}", day, reason),
+            AocError::InvalidArgument(reason) => write!(f, "invalid argument: {}", reason),
+            AocError::Io(reason) => write!(f, "reading the input: {}", reason),
+        }
+    }
+}
+
+impl Error for AocError {}
+
+impl From<io::Error> for AocError {
+    fn from(e: io::Error) -> Self {
+        AocError::Io(e.to_string())
+    }
+}
+
+/// 1-based column at which `token`, a subslice of `line`, starts.
+pub(crate) fn column(line: &str, token: &str) -> usize {
+    let offset = (token.as_ptr() as usize).saturating_sub(line.as_ptr() as usize);
+    line.get(..offset)
+        .map_or(1, |before| before.chars().count() + 1)
+}
+
+/// Parses `token`, a subslice of the `line_idx`-th (0-based) line, reporting
+/// failures at its position.
+pub(crate) fn parse_token<T>(
+    day: u32,
+    line_idx: usize,
+    line: &str,
+    token: &str,
+) -> Result<T, AocError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    token
+        .parse()
+        .map_err(|e| AocError::parse(day, line_idx + 1, column(line, token), token, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column() {
+        let line = "1-3 a: abcde";
+        assert_eq!(column(line, &line[7..]), 8);
+        assert_eq!(column(line, line), 1);
+    }
+
+    #[test]
+    fn test_parse_token() {
+        let line = "mem[8] = 1x";
+        let number: Result<u64, AocError> = parse_token(14, 2, line, &line[9..]);
+        assert_eq!(
+            number,
+            Err(AocError::parse(
+                14,
+                3,
+                10,
+                "1x",
+                "invalid digit found in string"
+            ))
+        );
+        assert_eq!(
+            number.unwrap_err().to_string(),
+            "day 14, line 3, column 10: invalid digit found in string: \"1x\""
+        );
+    }
+
+    #[test]
+    fn test_offset_lines() {
+        let err = AocError::parse(4, 2, 5, "hgt", "duplicate field").offset_lines(10);
+        assert_eq!(err, AocError::parse(4, 12, 5, "hgt", "duplicate field"));
+    }
+}
@@ -0,0 +1,661 @@
+use crate::day04::EyeColor;
+use crate::{day11, AocError};
+use rand::seq::{index, SliceRandom};
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Days with an input generator.
+pub const DAYS: &[u32] = &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+
+/// Random puzzle input for `day` that both parts can solve. The same seed
+/// always gives the same input.
+///
+/// `size` is the number of lines of the inputs with one record per line,
+/// of passports or answer groups, of numbers past the preamble, of slots of
+/// the bus schedule, or of starting numbers. It's the side of the seat grid
+/// of day 11 and the number of nearby tickets of day 16.
+pub fn generate(day: u32, size: usize, seed: u64) -> Result<String, AocError> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let min_size = match day {
+        1 => 5,
+        5 | 11 => 2,
+        9 => 26,
+        _ => 1,
+    };
+    if size < min_size {
+        return Err(AocError::invalid_argument(format!(
+            "day {} inputs need a size of at least {}",
+            day, min_size
+        )));
+    }
+
+    match day {
+        1 => Ok(expense_report(&mut rng, size)),
+        2 => Ok(passwords(&mut rng, size)),
+        3 => Ok(toboggan_map(&mut rng, size)),
+        4 => Ok(passports(&mut rng, size)),
+        5 => boarding_passes(&mut rng, size),
+        6 => Ok(answer_groups(&mut rng, size)),
+        7 => Ok(bag_rules(&mut rng, size)),
+        8 => Ok(program(&mut rng, size)),
+        9 => xmas_numbers(&mut rng, size),
+        10 => Ok(adapters(&mut rng, size)),
+        11 => Ok(seat_grid(&mut rng, size)),
+        12 => Ok(navigation(&mut rng, size)),
+        13 => Ok(bus_schedule(&mut rng, size)),
+        14 => Ok(bitmask_program(&mut rng, size)),
+        15 => Ok(starting_numbers(&mut rng, size)),
+        16 => Ok(ticket_notes(&mut rng, size)),
+        _ => Err(AocError::invalid_argument(format!(
+            "there is no input generator for day {}",
+            day
+        ))),
+    }
+}
+
+fn letter(rng: &mut impl Rng) -> char {
+    rng.gen_range(b'a'..=b'z') as char
+}
+
+/// Entries like the puzzle's, mostly over 1010 so that few of them add up
+/// to 2020, along with a pair and a triple that do.
+fn expense_report(rng: &mut impl Rng, size: usize) -> String {
+    let (x, y) = (rng.gen_range(1..=300), rng.gen_range(1..=300));
+    let a = rng.gen_range(301..=1009);
+    let mut entries: Vec<u32> = vec![x, y, 2020 - x - y, a, 2020 - a];
+    while entries.len() < size {
+        entries.push(rng.gen_range(1011..=2019));
+    }
+    entries.shuffle(rng);
+
+    entries.iter().map(|e| format!("{}\n", e)).collect()
+}
+
+/// Rows like `1-3 a: abcde`, with both positions inside the password.
+fn passwords(rng: &mut impl Rng, size: usize) -> String {
+    let mut input = String::new();
+    for _ in 0..size {
+        let lb = rng.gen_range(1..=10);
+        let ub = rng.gen_range(lb..=20);
+        let character = letter(rng);
+        let len = rng.gen_range(ub..=ub + 10);
+        // Bias towards the policy letter so some passwords are valid
+        let password: String = (0..len)
+            .map(|_| {
+                if rng.gen_bool(0.4) {
+                    character
+                } else {
+                    letter(rng)
+                }
+            })
+            .collect();
+        input.push_str(&format!("{}-{} {}: {}\n", lb, ub, character, password));
+    }
+    input
+}
+
+/// A map as wide as the puzzle's, about a fifth of it trees.
+fn toboggan_map(rng: &mut impl Rng, size: usize) -> String {
+    let mut input = String::new();
+    for _ in 0..size {
+        input.extend((0..31).map(|_| if rng.gen_bool(0.2) { '#' } else { '.' }));
+        input.push('\n');
+    }
+    input
+}
+
+/// Passports with their fields in any order, spread over a few lines. Some
+/// miss a required field or hold a value out of bounds.
+fn passports(rng: &mut impl Rng, size: usize) -> String {
+    let mut records = vec![];
+    for _ in 0..size {
+        let height = if rng.gen_bool(0.5) {
+            format!("hgt:{}cm", rng.gen_range(140..=200))
+        } else {
+            format!("hgt:{}in", rng.gen_range(55..=80))
+        };
+        let eye_color = EyeColor::ALL[rng.gen_range(0..EyeColor::ALL.len())];
+        let mut fields = vec![
+            format!("byr:{}", rng.gen_range(1910..=2010)),
+            format!("iyr:{}", rng.gen_range(2005..=2025)),
+            format!("eyr:{}", rng.gen_range(2015..=2035)),
+            height,
+            format!("hcl:#{:06x}", rng.gen_range(0..1 << 24)),
+            format!("ecl:{}", eye_color.code()),
+            format!("pid:{:09}", rng.gen_range(0..1_000_000_000)),
+            format!("cid:{}", rng.gen_range(100..=350)),
+        ];
+        // `cid` is last, and the only optional field
+        if rng.gen_bool(0.3) {
+            fields.pop();
+        }
+        if rng.gen_bool(0.1) {
+            fields.remove(rng.gen_range(0..fields.len()));
+        }
+        fields.shuffle(rng);
+
+        let mut record = String::new();
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                record.push(if rng.gen_bool(0.3) { '\n' } else { ' ' });
+            }
+            record.push_str(field);
+        }
+        records.push(record + "\n");
+    }
+    records.join("\n")
+}
+
+/// `size` passes for consecutive seats but one, shuffled.
+fn boarding_passes(rng: &mut impl Rng, size: usize) -> Result<String, AocError> {
+    if size > 1023 {
+        return Err(AocError::invalid_argument(
+            "the plane only has 1024 seats, one of them free",
+        ));
+    }
+    let first = rng.gen_range(0..1024 - size);
+    let free = rng.gen_range(first + 1..first + size);
+    let mut ids: Vec<usize> = (first..=first + size).filter(|id| *id != free).collect();
+    ids.shuffle(rng);
+
+    let mut input = String::new();
+    for id in ids {
+        let row = format!("{:07b}", id / 8)
+            .replace('0', "F")
+            .replace('1', "B");
+        let column = format!("{:03b}", id % 8)
+            .replace('0', "L")
+            .replace('1', "R");
+        input.push_str(&format!("{}{}\n", row, column));
+    }
+    Ok(input)
+}
+
+/// Groups of one to five people, answering questions the group mostly
+/// agrees on.
+fn answer_groups(rng: &mut impl Rng, size: usize) -> String {
+    let mut groups = vec![];
+    for _ in 0..size {
+        let common: Vec<char> = ('a'..='z').filter(|_| rng.gen_bool(0.3)).collect();
+        let mut group = String::new();
+        for _ in 0..rng.gen_range(1..=5) {
+            let mut answers: String = ('a'..='z')
+                .filter(|c| {
+                    let p = if common.contains(c) { 0.9 } else { 0.1 };
+                    rng.gen_bool(p)
+                })
+                .collect();
+            if answers.is_empty() {
+                answers.push(letter(rng));
+            }
+            group += &answers;
+            group.push('\n');
+        }
+        groups.push(group);
+    }
+    groups.join("\n")
+}
+
+const ADJECTIVES: &[&str] = &[
+    "bright", "clear", "dark", "dim", "dotted", "drab", "dull", "faded", "light", "mirrored",
+    "muted", "pale", "plaid", "posh", "shiny", "striped", "vibrant", "wavy",
+];
+const COLORS: &[&str] = &[
+    "aqua", "beige", "black", "blue", "bronze", "coral", "crimson", "gold", "gray", "green",
+    "indigo", "lime", "olive", "orange", "plum", "purple", "red", "salmon", "tan", "teal", "white",
+    "yellow",
+];
+
+/// Rules for `size` bags, one of them shiny gold. Bags only contain bags
+/// further down the list, so there are no cycles, and the number of bags
+/// inside any of them stays well within a `usize`.
+fn bag_rules(rng: &mut impl Rng, size: usize) -> String {
+    let mut names: Vec<String> = COLORS
+        .iter()
+        .flat_map(|c| ADJECTIVES.iter().map(move |a| format!("{} {}", a, c)))
+        .filter(|name| name != "shiny gold")
+        .collect();
+    names.shuffle(rng);
+    // Past every combination of words, number the adjectives
+    let mut extra = 0;
+    while names.len() < size {
+        extra += 1;
+        names.extend(
+            ADJECTIVES
+                .iter()
+                .map(|a| format!("{}{} {}", a, extra, COLORS[extra % COLORS.len()])),
+        );
+    }
+    names.truncate(size);
+    let gold = rng.gen_range(0..size);
+    names[gold] = "shiny gold".to_string();
+
+    const MAX_BAGS: u64 = 1_000_000_000_000;
+    let mut inside = vec![0u64; size];
+    let mut rules = vec![String::new(); size];
+    for i in (0..size).rev() {
+        let mut contents = vec![];
+        let children = if i + 1 < size {
+            rng.gen_range(0..=4)
+        } else {
+            0
+        };
+        for _ in 0..children {
+            let child = rng.gen_range(i + 1..size);
+            let qty: u64 = rng.gen_range(1..=5);
+            let bags = qty * (1 + inside[child]);
+            if contents.iter().any(|(c, _)| *c == child) || inside[i] + bags > MAX_BAGS {
+                continue;
+            }
+            inside[i] += bags;
+            contents.push((child, qty));
+        }
+
+        let contents = if contents.is_empty() {
+            "no other bags".to_string()
+        } else {
+            contents
+                .iter()
+                .map(|(child, qty)| {
+                    let plural = if *qty == 1 { "" } else { "s" };
+                    format!("{} {} bag{}", qty, names[*child], plural)
+                })
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        rules[i] = format!("{} bags contain {}.\n", names[i], contents);
+    }
+
+    rules.shuffle(rng);
+    rules.concat()
+}
+
+/// A program that loops because a single `nop` was turned into a `jmp`
+/// backwards. Nothing before it jumps past it, even when switched, so
+/// switching it back is the only fix.
+fn program(rng: &mut impl Rng, size: usize) -> String {
+    let corrupted = rng.gen_range(0..size);
+    let mut input = String::new();
+    for i in 0..size {
+        let (op, arg) = if i == corrupted {
+            ("jmp", -(rng.gen_range(0..=i) as i64))
+        } else {
+            let limit = if i < corrupted { corrupted } else { size };
+            let ahead = (limit - i).min(5) as i64;
+            match rng.gen_range(0..3) {
+                0 => ("acc", rng.gen_range(-50..=50)),
+                1 => ("nop", rng.gen_range(-(i.min(5) as i64)..=ahead)),
+                _ => ("jmp", rng.gen_range(1..=ahead)),
+            }
+        };
+        input.push_str(&format!("{} {:+}\n", op, arg));
+    }
+    input
+}
+
+/// A preamble of 25 numbers, then `size` numbers that are the sum of two
+/// different ones among the 25 before them, except for the last. That one
+/// adds up two neighbours of the preamble, which is less than any pair of
+/// the numbers before it adds up to.
+///
+/// Numbers at least double every 25 of them, so inputs past 1300 numbers or
+/// so don't fit an `isize`.
+fn xmas_numbers(rng: &mut impl Rng, size: usize) -> Result<String, AocError> {
+    const PREAMBLE: usize = 25;
+    let mut numbers: Vec<isize> = (100..200).collect();
+    numbers.shuffle(rng);
+    numbers.truncate(PREAMBLE);
+    for i in PREAMBLE..PREAMBLE + size - 1 {
+        let window = &numbers[i - PREAMBLE..i];
+        // Small terms keep the numbers growing as slowly as they can, and
+        // the oldest numbers tend to be the smallest
+        let first = window[rng.gen_range(0..3)];
+        // A sum differs from both its terms, so windows never hold a single
+        // value
+        let second = window
+            .iter()
+            .filter(|n| **n != first)
+            .min()
+            .copied()
+            .expect("windows hold different numbers");
+        let next = first.checked_add(second).ok_or_else(|| {
+            AocError::invalid_argument(format!(
+                "day 9 inputs of {} numbers don't fit an isize",
+                size
+            ))
+        })?;
+        numbers.push(next);
+    }
+    let start = rng.gen_range(0..PREAMBLE - 1);
+    numbers.push(numbers[start] + numbers[start + 1]);
+
+    Ok(numbers.iter().map(|n| format!("{}\n", n)).collect())
+}
+
+/// Adapters 1 or 3 jolts apart once sorted, shuffled.
+///
+/// Part 2 tries every order of leaving adapters out, so only three of them
+/// can be left out: the ones between two gaps of 1 jolt.
+fn adapters(rng: &mut impl Rng, size: usize) -> String {
+    let mut joltage = 0;
+    let mut adapters = vec![];
+    let (mut run, mut spare) = (0, 3);
+    for _ in 0..size {
+        let gap = match run {
+            0 if rng.gen_bool(0.6) => 1,
+            1 if spare > 0 && rng.gen_bool(0.3) => {
+                spare -= 1;
+                1
+            }
+            _ => 3,
+        };
+        run = if gap == 1 { run + 1 } else { 0 };
+        joltage += gap;
+        adapters.push(joltage);
+    }
+    adapters.shuffle(rng);
+
+    adapters.iter().map(|a| format!("{}\n", a)).collect()
+}
+
+type SeatStep = fn(&Vec<Vec<char>>) -> (Vec<Vec<char>>, bool);
+
+/// Seats still changing once the rules applied by `step` fall into a cycle,
+/// or after too many rounds.
+fn oscillating_seats(grid: &[Vec<char>], step: SeatStep) -> Vec<(usize, usize)> {
+    let mut previous = grid.to_vec();
+    let (mut current, mut changed) = step(&previous);
+    let mut rounds = 0;
+    while changed {
+        let (next, next_changed) = step(&current);
+        rounds += 1;
+        if next == previous || rounds > 1000 {
+            let mut seats = vec![];
+            for (i, row) in next.iter().enumerate() {
+                for (j, seat) in row.iter().enumerate() {
+                    if *seat != current[i][j] {
+                        seats.push((i, j));
+                    }
+                }
+            }
+            return seats;
+        }
+        previous = current;
+        current = next;
+        changed = next_changed;
+    }
+    vec![]
+}
+
+/// A square grid of empty seats (`L`) and floor (`.`).
+///
+/// Random layouts often never settle, flipping some seats back and forth
+/// forever. Those seats are turned into floor until the layout settles under
+/// the rules of both parts.
+fn seat_grid(rng: &mut impl Rng, size: usize) -> String {
+    let mut grid: Vec<Vec<char>> = (0..size)
+        .map(|_| {
+            (0..size)
+                .map(|_| if rng.gen_bool(0.8) { 'L' } else { '.' })
+                .collect()
+        })
+        .collect();
+
+    loop {
+        let mut seats = oscillating_seats(&grid, day11::switch_seats);
+        seats.extend(oscillating_seats(&grid, day11::switch_seats_p2));
+        if seats.is_empty() {
+            break;
+        }
+        for (i, j) in seats {
+            grid[i][j] = '.';
+        }
+    }
+
+    grid.iter()
+        .map(|row| row.iter().collect::<String>() + "\n")
+        .collect()
+}
+
+/// Short moves, turns by multiples of 90 degrees and longer moves forward,
+/// like the puzzle's.
+fn navigation(rng: &mut impl Rng, size: usize) -> String {
+    let mut input = String::new();
+    for _ in 0..size {
+        let instruction = match rng.gen_range(0..7) {
+            0..=3 => format!(
+                "{}{}",
+                ['N', 'S', 'E', 'W'][rng.gen_range(0..4)],
+                rng.gen_range(1..=5)
+            ),
+            4 | 5 => format!(
+                "{}{}",
+                ['L', 'R'][rng.gen_range(0..2)],
+                90 * rng.gen_range(1..=3)
+            ),
+            _ => format!("F{}", rng.gen_range(1..=100)),
+        };
+        input.push_str(&instruction);
+        input.push('\n');
+    }
+    input
+}
+
+/// A schedule of `size` slots, the first one being a bus. Buses have
+/// different prime ids, and there are at most 12 of them below 1000 so the
+/// timestamp of part 2 fits an `i128`.
+fn bus_schedule(rng: &mut impl Rng, size: usize) -> String {
+    let primes: Vec<u32> = (13..1000u32)
+        .filter(|n| (2..).take_while(|d| d * d <= *n).all(|d| n % d != 0))
+        .collect();
+    let buses = size.div_ceil(8).min(12);
+    let mut slots: Vec<usize> = (1..size).collect();
+    slots.shuffle(rng);
+    slots.truncate(buses - 1);
+    slots.push(0);
+
+    let mut schedule = vec!["x".to_string(); size];
+    for (slot, id) in slots.iter().zip(primes.choose_multiple(rng, buses)) {
+        schedule[*slot] = id.to_string();
+    }
+    format!(
+        "{}\n{}\n",
+        rng.gen_range(100_000..1_000_000),
+        schedule.join(",")
+    )
+}
+
+/// A program starting with a mask. Masks have at most 9 floating bits so
+/// part 2 writes at most 512 addresses per instruction.
+fn bitmask_program(rng: &mut impl Rng, size: usize) -> String {
+    let mut input = String::new();
+    for i in 0..size {
+        if i == 0 || rng.gen_bool(0.2) {
+            let mut mask: Vec<char> = (0..36)
+                .map(|_| if rng.gen_bool(0.5) { '0' } else { '1' })
+                .collect();
+            for _ in 0..rng.gen_range(0..=9) {
+                mask[rng.gen_range(0..36)] = 'X';
+            }
+            input.push_str(&format!("mask = {}\n", mask.iter().collect::<String>()));
+        } else {
+            let address = rng.gen_range(0..1u64 << 36);
+            let value = rng.gen_range(0..1u64 << 36);
+            input.push_str(&format!("mem[{}] = {}\n", address, value));
+        }
+    }
+    input
+}
+
+/// `size` different starting numbers, small like the puzzle's.
+fn starting_numbers(rng: &mut impl Rng, size: usize) -> String {
+    let numbers: Vec<String> = index::sample(rng, size * 3, size)
+        .iter()
+        .map(|n| n.to_string())
+        .collect();
+    numbers.join(",") + "\n"
+}
+
+const FIELDS: &[&str] = &[
+    "departure location",
+    "departure station",
+    "departure platform",
+    "departure track",
+    "departure date",
+    "departure time",
+    "arrival location",
+    "arrival station",
+    "arrival platform",
+    "arrival track",
+    "class",
+    "duration",
+    "price",
+    "route",
+    "row",
+    "seat",
+    "train",
+    "type",
+    "wagon",
+    "zone",
+];
+
+/// Ticket notes with `size` nearby tickets, some of them invalid.
+///
+/// Each column only holds values from its own band, and the n-th field
+/// accepts the bands from the n-th onwards. The last field then fits a
+/// single column, the one before it two, and so on, so the fields can be
+/// told apart one at a time.
+fn ticket_notes(rng: &mut impl Rng, size: usize) -> String {
+    const FIRST: usize = 50;
+    const BAND: usize = 40;
+    let n = FIELDS.len();
+    let last = FIRST + BAND * n - 1;
+
+    let mut ranks: Vec<usize> = (0..n).collect();
+    ranks.shuffle(rng);
+    let mut fields: Vec<usize> = (0..n).collect();
+    fields.shuffle(rng);
+
+    let mut input = String::new();
+    for field in fields {
+        let start = FIRST + BAND * ranks[field];
+        let split = rng.gen_range(start..last);
+        input.push_str(&format!(
+            "{}: {}-{} or {}-{}\n",
+            FIELDS[field],
+            start,
+            split,
+            split + 1,
+            last
+        ));
+    }
+
+    // Column `c` holds the values of field `columns[c]`
+    let mut columns: Vec<usize> = (0..n).collect();
+    columns.shuffle(rng);
+    let bands: Vec<usize> = columns.iter().map(|field| ranks[*field]).collect();
+
+    let ticket = |rng: &mut dyn RngCore, valid: bool| {
+        let mut values: Vec<usize> = bands
+            .iter()
+            .map(|band| FIRST + BAND * band + rng.gen_range(0..BAND))
+            .collect();
+        if !valid {
+            values[rng.gen_range(0..n)] = if rng.gen_bool(0.5) {
+                rng.gen_range(0..FIRST)
+            } else {
+                rng.gen_range(last + 1..last + 200)
+            };
+        }
+        values
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+    };
+
+    input.push_str(&format!("\nyour ticket:\n{}\n", ticket(rng, true)));
+    input.push_str("\nnearby tickets:\n");
+    for i in 0..size {
+        // At least one valid ticket is needed to tell the fields apart
+        let valid = i == 0 || rng.gen_bool(0.8);
+        input.push_str(&ticket(rng, valid));
+        input.push('\n');
+    }
+    input
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Part;
+
+    #[test]
+    fn test_generated_inputs_solve() {
+        for day in DAYS {
+            for seed in 0..3 {
+                let input = generate(*day, 30, seed).unwrap();
+                // Part 2 of day 15 plays the same game for much longer
+                let parts = if *day == 15 {
+                    &Part::ALL[..1]
+                } else {
+                    &Part::ALL[..]
+                };
+                for part in parts {
+                    let answer = crate::day(*day).unwrap().run(&input, *part);
+                    assert!(
+                        answer.is_ok(),
+                        "day {} part {} seed {}: {:?}",
+                        day,
+                        part,
+                        seed,
+                        answer
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_seat_grid_settles() {
+        // Large enough for most random layouts to oscillate
+        for seed in 0..2 {
+            let input = generate(11, 60, seed).unwrap();
+            let grid = day11::parse_input(&input).unwrap();
+            assert!(oscillating_seats(&grid, day11::switch_seats).is_empty());
+            assert!(oscillating_seats(&grid, day11::switch_seats_p2).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_seed() {
+        for day in DAYS {
+            assert_eq!(
+                generate(*day, 30, 42),
+                generate(*day, 30, 42),
+                "day {}",
+                day
+            );
+            assert_ne!(
+                generate(*day, 30, 42),
+                generate(*day, 30, 43),
+                "day {}",
+                day
+            );
+        }
+        assert_eq!(generate(2, 5, 1).unwrap().lines().count(), 5);
+        assert_eq!(generate(9, 30, 1).unwrap().lines().count(), 55);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(generate(17, 10, 0).is_err());
+        assert!(generate(1, 4, 0).is_err());
+        assert!(generate(9, 25, 0).is_err());
+        assert!(generate(9, 1000, 0).is_ok());
+        assert!(generate(9, 5000, 0).is_err());
+        assert!(generate(11, 1, 0).is_err());
+        assert!(generate(5, 1024, 0).is_err());
+        assert!(generate(5, 1023, 0).is_ok());
+    }
+}
@@ -1,5 +1,16 @@
 #![allow(dead_code)]
 use aoc_runner_derive::aoc_lib;
+use serde::{Serialize, Serializer};
+use std::fmt::{self, Display};
+use std::io::BufRead;
+use std::path::PathBuf;
+
+pub mod bench;
+pub mod error;
+pub mod gen;
+pub mod runner;
+pub mod stream;
+pub mod verify;
 
 pub mod day01;
 pub mod day02;
@@ -19,4 +30,246 @@ pub mod day15;
 pub mod day16;
 pub mod day17;
 
+pub use error::AocError;
+
 aoc_lib! { year = 2020 }
+
+/// Common interface implemented by every day of the calendar.
+///
+/// `parse` plays the role of the cargo-aoc generator, turning the raw puzzle
+/// input into `Input`, which is then shared by both parts.
+pub trait Solver {
+    type Input;
+    type Output: Display;
+
+    fn parse(input: &str) -> Result<Self::Input, AocError>;
+    fn part1(input: &Self::Input) -> Result<Self::Output, AocError>;
+    fn part2(input: &Self::Input) -> Result<Self::Output, AocError>;
+}
+
+/// Days that can also solve their input while reading it line by line,
+/// for inputs too big to hold in memory as a single `&str`.
+pub trait StreamSolver: Solver {
+    fn part1_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError>;
+    fn part2_stream(reader: &mut dyn BufRead) -> Result<Self::Output, AocError>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Part {
+    One,
+    Two,
+}
+
+impl Part {
+    pub const ALL: [Part; 2] = [Part::One, Part::Two];
+
+    pub fn from_number(n: u32) -> Option<Part> {
+        match n {
+            1 => Some(Part::One),
+            2 => Some(Part::Two),
+            _ => None,
+        }
+    }
+
+    pub fn number(self) -> u32 {
+        match self {
+            Part::One => 1,
+            Part::Two => 2,
+        }
+    }
+}
+
+impl Serialize for Part {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.number())
+    }
+}
+
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.number())
+    }
+}
+
+/// Entry of the registry of available days, with the solver type erased so
+/// every day can be stored in the same list.
+pub struct Day {
+    pub number: u32,
+    parse: Parser,
+    stream: Option<Streamer>,
+}
+
+type Parser = fn(&str) -> Result<Box<dyn ParsedInput>, AocError>;
+type Streamer = fn(&mut dyn BufRead, Part) -> Result<String, AocError>;
+
+/// Output of a day's generator, ready to solve either part as many times as
+/// needed without parsing again.
+pub trait ParsedInput {
+    fn solve(&self, part: Part) -> Result<String, AocError>;
+}
+
+struct Parsed<S: Solver>(S::Input);
+
+impl<S: Solver> ParsedInput for Parsed<S> {
+    fn solve(&self, part: Part) -> Result<String, AocError> {
+        let answer = match part {
+            Part::One => S::part1(&self.0)?,
+            Part::Two => S::part2(&self.0)?,
+        };
+
+        Ok(answer.to_string())
+    }
+}
+
+fn parse_solver<S: Solver + 'static>(input: &str) -> Result<Box<dyn ParsedInput>, AocError> {
+    Ok(Box::new(Parsed::<S>(S::parse(input)?)))
+}
+
+fn stream_solver<S: StreamSolver>(
+    reader: &mut dyn BufRead,
+    part: Part,
+) -> Result<String, AocError> {
+    let answer = match part {
+        Part::One => S::part1_stream(reader)?,
+        Part::Two => S::part2_stream(reader)?,
+    };
+
+    Ok(answer.to_string())
+}
+
+impl Day {
+    const fn new<S: Solver + 'static>(number: u32) -> Self {
+        Day {
+            number,
+            parse: parse_solver::<S>,
+            stream: None,
+        }
+    }
+
+    const fn streaming<S: StreamSolver + 'static>(number: u32) -> Self {
+        Day {
+            number,
+            parse: parse_solver::<S>,
+            stream: Some(stream_solver::<S>),
+        }
+    }
+
+    /// Whether the day can be solved with `run_stream`.
+    pub fn can_stream(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Solves `part` while reading the input from `reader`, without holding
+    /// all of it in memory.
+    pub fn run_stream(&self, reader: &mut dyn BufRead, part: Part) -> Result<String, AocError> {
+        match self.stream {
+            Some(stream) => stream(reader, part),
+            None => Err(AocError::invalid_argument(format!(
+                "day {} cannot be solved from a stream",
+                self.number
+            ))),
+        }
+    }
+
+    /// Runs the generator of the day on `input`.
+    pub fn parse(&self, input: &str) -> Result<Box<dyn ParsedInput>, AocError> {
+        // cargo-aoc strips the trailing newline before handing the input over
+        (self.parse)(input.trim_end_matches('\n'))
+    }
+
+    /// Parses `input` and solves `part`, returning the displayed answer.
+    pub fn run(&self, input: &str, part: Part) -> Result<String, AocError> {
+        self.parse(input)?.solve(part)
+    }
+}
+
+/// Every day solved so far, in calendar order.
+pub const DAYS: &[Day] = &[
+    Day::streaming::<day01::Day01>(1),
+    Day::streaming::<day02::Day02>(2),
+    Day::new::<day03::Day03>(3),
+    Day::new::<day04::Day04>(4),
+    Day::streaming::<day05::Day05>(5),
+    Day::new::<day06::Day06>(6),
+    Day::new::<day07::Day07>(7),
+    Day::streaming::<day08::Day08>(8),
+    Day::streaming::<day09::Day09>(9),
+    Day::streaming::<day10::Day10>(10),
+    Day::new::<day11::Day11>(11),
+    Day::streaming::<day12::Day12>(12),
+    Day::new::<day13::Day13>(13),
+    Day::streaming::<day14::Day14>(14),
+    Day::new::<day15::Day15>(15),
+    Day::new::<day16::Day16>(16),
+    Day::new::<day17::Day17>(17),
+];
+
+/// Default location of the puzzle input of a day, relative to the crate root.
+pub fn input_path(day: u32) -> PathBuf {
+    PathBuf::from(format!("input/2020/day{}.txt", day))
+}
+
+/// Looks up a day in the registry.
+pub fn day(number: u32) -> Option<&'static Day> {
+    DAYS.iter().find(|d| d.number == number)
+}
+
+/// Runs the generator and the given part of a day against the raw puzzle
+/// input, returning the answer as it would be printed by cargo-aoc.
+pub fn solve(day_number: u32, part: u32, input: &str) -> Result<String, AocError> {
+    let part = Part::from_number(part)
+        .ok_or_else(|| AocError::invalid_argument(format!("part {} does not exist", part)))?;
+    match day(day_number) {
+        Some(d) => d.run(input, part),
+        None => Err(AocError::invalid_argument(format!(
+            "day {} is not implemented",
+            day_number
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_order() {
+        let numbers: Vec<u32> = DAYS.iter().map(|d| d.number).collect();
+        assert_eq!(numbers, (1..=17).collect::<Vec<u32>>());
+        assert!(day(18).is_none());
+    }
+
+    #[test]
+    fn test_solve() {
+        let input = "1721\n979\n366\n299\n675\n1456\n";
+        assert_eq!(solve(1, 1, input).unwrap(), "514579");
+        assert_eq!(solve(1, 2, input).unwrap(), "241861950");
+        assert!(solve(1, 3, input).is_err());
+        assert!(solve(17, 2, ".#.\n..#\n###").is_err());
+    }
+
+    #[test]
+    fn test_run_stream() {
+        let mut inputs = vec![(1, "1721\n979\n366\n299\n675\n1456\n".to_string())];
+        for d in [2, 5, 8, 14] {
+            inputs.push((d, gen::generate(d, 50, 7).unwrap()));
+        }
+        for (number, input) in inputs {
+            let d = day(number).unwrap();
+            for part in Part::ALL.iter() {
+                assert_eq!(
+                    d.run_stream(&mut input.as_bytes(), *part),
+                    d.run(&input, *part),
+                    "day {} part {}",
+                    number,
+                    part
+                );
+            }
+        }
+        assert!(!day(3).unwrap().can_stream());
+        assert!(day(3)
+            .unwrap()
+            .run_stream(&mut "".as_bytes(), Part::One)
+            .is_err());
+    }
+}
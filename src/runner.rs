@@ -0,0 +1,238 @@
+use crate::bench::{run_with_timeout, Status};
+use crate::{Day, Part};
+use serde::{Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A single part of a day to solve, along with its puzzle input.
+#[derive(Clone)]
+pub struct Job {
+    pub day: &'static Day,
+    pub part: Part,
+    pub input: Arc<String>,
+}
+
+/// Outcome of a job. `answer` is only set when `status` is `Ok`, and
+/// `error` otherwise.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RunResult {
+    pub day: u32,
+    pub part: Part,
+    pub status: Status,
+    pub answer: Option<String>,
+    pub error: Option<String>,
+    #[serde(rename = "generator_ns", serialize_with = "serialize_nanos")]
+    pub generator_time: Duration,
+    #[serde(rename = "solver_ns", serialize_with = "serialize_nanos")]
+    pub solver_time: Duration,
+    /// SHA-256 of the puzzle input, in hex.
+    pub input_hash: String,
+}
+
+fn serialize_nanos<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_nanos() as u64)
+}
+
+/// Hex encoded SHA-256 of `input`.
+pub fn input_hash(input: &str) -> String {
+    format!("{:x}", Sha256::digest(input.as_bytes()))
+}
+
+impl RunResult {
+    pub fn elapsed(&self) -> Duration {
+        self.generator_time + self.solver_time
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(s) => *s,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(s) => s.to_string(),
+            Err(_) => "panicked".to_string(),
+        },
+    }
+}
+
+/// Runs `job`, turning panics into a `Panicked` result and giving up after
+/// `timeout`.
+pub fn run_job(job: Job, timeout: Duration) -> RunResult {
+    let mut result = RunResult {
+        day: job.day.number,
+        part: job.part,
+        status: Status::Ok,
+        answer: None,
+        error: None,
+        generator_time: Duration::default(),
+        solver_time: Duration::default(),
+        input_hash: input_hash(&job.input),
+    };
+
+    let timed = run_with_timeout(timeout, move || {
+        let mut times = (Duration::default(), Duration::default());
+        let answer = panic::catch_unwind(AssertUnwindSafe(|| {
+            let start = Instant::now();
+            let parsed = job.day.parse(&job.input);
+            times.0 = start.elapsed();
+
+            let start = Instant::now();
+            let answer = parsed?.solve(job.part);
+            times.1 = start.elapsed();
+            answer
+        }));
+        (times, answer)
+    });
+
+    match timed {
+        Ok(((generator_time, solver_time), answer)) => {
+            result.generator_time = generator_time;
+            result.solver_time = solver_time;
+            match answer {
+                Ok(Ok(answer)) => result.answer = Some(answer),
+                Ok(Err(e)) => {
+                    result.status = Status::Error;
+                    result.error = Some(e.to_string());
+                }
+                Err(payload) => {
+                    result.status = Status::Panicked;
+                    result.error = Some(panic_message(payload));
+                }
+            }
+        }
+        Err(status) => {
+            result.status = status;
+            result.solver_time = timeout;
+            result.error = Some(match status {
+                Status::Timeout => format!("timed out after {:?}", timeout),
+                _ => status.to_string(),
+            });
+        }
+    }
+
+    result
+}
+
+/// Runs every job on a pool of `workers` threads, yielding the results as
+/// they finish.
+pub fn run_each(jobs: Vec<Job>, workers: usize, timeout: Duration) -> mpsc::IntoIter<RunResult> {
+    let (job_sender, job_receiver) = mpsc::channel();
+    let job_receiver = Arc::new(Mutex::new(job_receiver));
+    let (result_sender, result_receiver) = mpsc::channel();
+
+    for _ in 0..workers.max(1) {
+        let jobs = Arc::clone(&job_receiver);
+        let results = result_sender.clone();
+        // Workers stop on their own once the queue is drained
+        thread::spawn(move || loop {
+            // The lock is released as soon as a job is taken
+            let job = match jobs.lock().unwrap().recv() {
+                Ok(job) => job,
+                Err(_) => break,
+            };
+            if results.send(run_job(job, timeout)).is_err() {
+                break;
+            }
+        });
+    }
+
+    for job in jobs {
+        job_sender.send(job).unwrap();
+    }
+
+    result_receiver.into_iter()
+}
+
+/// Runs every job on a pool of `workers` threads, returning the results in
+/// day and part order.
+pub fn run_all(jobs: Vec<Job>, workers: usize, timeout: Duration) -> Vec<RunResult> {
+    let mut results: Vec<RunResult> = run_each(jobs, workers, timeout).collect();
+    results.sort_by_key(|r| (r.day, r.part));
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_INPUT: &str = "1721\n979\n366\n299\n675\n1456";
+
+    fn job(day: u32, part: Part, input: &str) -> Job {
+        Job {
+            day: crate::day(day).unwrap(),
+            part,
+            input: Arc::new(input.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_run_job() {
+        let timeout = Duration::from_secs(10);
+        let result = run_job(job(1, Part::One, EXAMPLE_INPUT), timeout);
+        assert_eq!(result.status, Status::Ok);
+        assert_eq!(result.answer, Some("514579".to_string()));
+        assert_eq!(result.input_hash, input_hash(EXAMPLE_INPUT));
+
+        let result = run_job(job(1, Part::One, "1721\nx"), timeout);
+        assert_eq!(result.status, Status::Error);
+        assert_eq!(result.answer, None);
+
+        // Day 17 still indexes out of its grid on any cycle
+        let result = run_job(job(17, Part::One, ".#.\n..#\n###"), timeout);
+        assert_eq!(result.status, Status::Panicked);
+        assert!(result.error.unwrap().contains("index out of bounds"));
+    }
+
+    #[test]
+    fn test_input_hash() {
+        assert_eq!(
+            input_hash(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn test_serialize() {
+        let result = RunResult {
+            day: 1,
+            part: Part::Two,
+            status: Status::Error,
+            answer: None,
+            error: Some("no solution".to_string()),
+            generator_time: Duration::from_micros(3),
+            solver_time: Duration::from_nanos(42),
+            input_hash: input_hash(""),
+        };
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["part"], 2);
+        assert_eq!(json["status"], "error");
+        assert_eq!(json["answer"], serde_json::Value::Null);
+        assert_eq!(json["generator_ns"], 3000);
+        assert_eq!(json["solver_ns"], 42);
+    }
+
+    #[test]
+    fn test_run_all() {
+        let jobs = vec![
+            job(1, Part::Two, EXAMPLE_INPUT),
+            job(17, Part::One, ".#.\n..#\n###"),
+            job(1, Part::One, EXAMPLE_INPUT),
+        ];
+        let results = run_all(jobs, 2, Duration::from_secs(10));
+        let summary: Vec<(u32, Part, Status)> =
+            results.iter().map(|r| (r.day, r.part, r.status)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, Part::One, Status::Ok),
+                (1, Part::Two, Status::Ok),
+                (17, Part::One, Status::Panicked)
+            ]
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use crate::AocError;
+use std::io::BufRead;
+
+/// Parses the lines of a reader one at a time, buffering only the current
+/// line. Created by [`records`].
+pub struct Records<R, F> {
+    reader: R,
+    parse: F,
+    line: String,
+    idx: usize,
+    /// Blank lines read but not handed to `parse` yet, as trailing ones are
+    /// dropped like `Day::parse` does.
+    blanks: usize,
+    /// Whether `line` holds a line read after the pending blanks.
+    held: bool,
+    failed: bool,
+}
+
+/// Parses every line of `reader` with `parse`, which receives the 0-based
+/// index of the line and the line without its terminator.
+pub fn records<R, F, T>(reader: R, parse: F) -> Records<R, F>
+where
+    R: BufRead,
+    F: FnMut(usize, &str) -> Result<T, AocError>,
+{
+    Records {
+        reader,
+        parse,
+        line: String::new(),
+        idx: 0,
+        blanks: 0,
+        held: false,
+        failed: false,
+    }
+}
+
+impl<R, F, T> Iterator for Records<R, F>
+where
+    R: BufRead,
+    F: FnMut(usize, &str) -> Result<T, AocError>,
+{
+    type Item = Result<T, AocError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.held {
+                let idx = self.idx;
+                self.idx += 1;
+                if self.blanks > 0 {
+                    self.blanks -= 1;
+                    return Some((self.parse)(idx, ""));
+                }
+                self.held = false;
+                return Some((self.parse)(idx, &self.line));
+            }
+            if self.failed {
+                return None;
+            }
+
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {
+                    let len = self
+                        .line
+                        .trim_end_matches('\n')
+                        .trim_end_matches('\r')
+                        .len();
+                    self.line.truncate(len);
+                    if self.line.is_empty() {
+                        self.blanks += 1;
+                    } else {
+                        self.held = true;
+                    }
+                }
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, Read};
+
+    fn lines(input: &str) -> Vec<(usize, String)> {
+        records(input.as_bytes(), |idx, l| Ok((idx, l.to_string())))
+            .collect::<Result<_, AocError>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_records() {
+        assert_eq!(
+            lines("a\r\n\nb\n\n\n"),
+            vec![
+                (0, "a".to_string()),
+                (1, "".to_string()),
+                (2, "b".to_string())
+            ]
+        );
+        assert_eq!(lines(""), vec![]);
+        assert_eq!(lines("a").len(), 1);
+    }
+
+    #[test]
+    fn test_records_errors() {
+        let mut parsed = records("1\nx\n3".as_bytes(), |idx, l| {
+            crate::error::parse_token::<u32>(1, idx, l, l)
+        });
+        assert_eq!(parsed.next(), Some(Ok(1)));
+        assert!(matches!(
+            parsed.next(),
+            Some(Err(AocError::Parse { line: 2, .. }))
+        ));
+        assert_eq!(parsed.next(), Some(Ok(3)));
+
+        let invalid = io::Cursor::new(vec![b'1', b'\n', 0xff]).take(16);
+        let mut parsed = records(io::BufReader::new(invalid), |_, l| Ok(l.len()));
+        assert_eq!(parsed.next(), Some(Ok(1)));
+        assert!(matches!(parsed.next(), Some(Err(AocError::Io(_)))));
+        assert_eq!(parsed.next(), None);
+    }
+}
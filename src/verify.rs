@@ -0,0 +1,206 @@
+use crate::bench::{run_with_timeout, Status};
+use crate::{AocError, Day, Part};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Known-good answers, stored as TOML with a table per day:
+///
+/// ```toml
+/// [day1]
+/// part1 = "514579"
+/// part2 = "241861950"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Answers {
+    answers: BTreeMap<(u32, Part), String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct DayAnswers {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    part1: Option<Answer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    part2: Option<Answer>,
+}
+
+/// Answers may be written as plain TOML integers as well as strings.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Answer {
+    Number(i64),
+    Text(String),
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Answer::Number(n) => write!(f, "{}", n),
+            Answer::Text(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl Answers {
+    pub fn from_toml(toml: &str) -> Result<Self, AocError> {
+        let days: BTreeMap<String, DayAnswers> = toml::from_str(toml)
+            .map_err(|e| AocError::invalid_argument(format!("answers file: {}", e)))?;
+
+        let mut answers = Answers::default();
+        for (key, day) in days {
+            let number = key
+                .strip_prefix("day")
+                .and_then(|n| n.parse().ok())
+                .ok_or_else(|| {
+                    AocError::invalid_argument(format!(
+                        "answers file: expected a table like `[day1]`, found `[{}]`",
+                        key
+                    ))
+                })?;
+            if let Some(a) = day.part1 {
+                answers.set(number, Part::One, a.to_string());
+            }
+            if let Some(a) = day.part2 {
+                answers.set(number, Part::Two, a.to_string());
+            }
+        }
+
+        Ok(answers)
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut days: BTreeMap<u32, DayAnswers> = BTreeMap::new();
+        for ((day, part), answer) in &self.answers {
+            let entry = days.entry(*day).or_default();
+            let answer = Some(Answer::Text(answer.clone()));
+            match part {
+                Part::One => entry.part1 = answer,
+                Part::Two => entry.part2 = answer,
+            }
+        }
+
+        // Tables are written in calendar order rather than sorted as strings
+        days.iter()
+            .map(|(day, answers)| {
+                format!(
+                    "[day{}]\n{}",
+                    day,
+                    toml::to_string(answers).expect("answers are plain strings")
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    pub fn get(&self, day: u32, part: Part) -> Option<&str> {
+        self.answers.get(&(day, part)).map(|a| a.as_str())
+    }
+
+    pub fn set(&mut self, day: u32, part: Part, answer: String) {
+        self.answers.insert((day, part), answer);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// The solver matches the recorded answer.
+    Pass,
+    /// The solver disagrees with the recorded answer, or didn't produce one.
+    Fail,
+    /// The solver produced an answer but there's nothing to compare it with.
+    Missing,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Pass => write!(f, "pass"),
+            Verdict::Fail => write!(f, "fail"),
+            Verdict::Missing => write!(f, "missing"),
+        }
+    }
+}
+
+/// Outcome of checking one part of a day.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Check {
+    pub day: u32,
+    pub part: Part,
+    pub verdict: Verdict,
+    pub expected: Option<String>,
+    /// The answer, or why the solver didn't give one.
+    pub actual: Result<String, String>,
+}
+
+/// Runs both parts of `day` against `input` and checks them against
+/// `answers`. Parts taking longer than `timeout` count as failures.
+pub fn verify_day(
+    day: &'static Day,
+    input: &str,
+    answers: &Answers,
+    timeout: Duration,
+) -> Vec<Check> {
+    Part::ALL
+        .iter()
+        .map(|&part| {
+            let owned = input.to_string();
+            let actual = match run_with_timeout(timeout, move || day.run(&owned, part)) {
+                Ok(Ok(answer)) => Ok(answer),
+                Ok(Err(e)) => Err(e.to_string()),
+                Err(Status::Timeout) => Err(format!("timed out after {:?}", timeout)),
+                Err(status) => Err(status.to_string()),
+            };
+            let expected = answers.get(day.number, part).map(|a| a.to_string());
+            let verdict = match (&expected, &actual) {
+                (Some(e), Ok(a)) if e == a => Verdict::Pass,
+                (None, Ok(_)) => Verdict::Missing,
+                _ => Verdict::Fail,
+            };
+
+            Check {
+                day: day.number,
+                part,
+                verdict,
+                expected,
+                actual,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANSWERS: &str = "[day1]\npart1 = \"514579\"\npart2 = 1\n\n[day2]\npart2 = \"1\"\n";
+
+    #[test]
+    fn test_answers() {
+        let answers = Answers::from_toml(ANSWERS).unwrap();
+        assert_eq!(answers.get(1, Part::One), Some("514579"));
+        assert_eq!(answers.get(1, Part::Two), Some("1"));
+        assert_eq!(answers.get(2, Part::One), None);
+        assert_eq!(Answers::from_toml(&answers.to_toml()).unwrap(), answers);
+
+        assert!(Answers::from_toml("[one]\npart1 = 1").is_err());
+        assert!(Answers::from_toml("[day1]\npart3 = 1").is_err());
+    }
+
+    #[test]
+    fn test_verify_day() {
+        let answers = Answers::from_toml(ANSWERS).unwrap();
+        let day = crate::day(1).unwrap();
+        let timeout = Duration::from_secs(10);
+        let checks = verify_day(day, "1721\n979\n366\n299\n675\n1456", &answers, timeout);
+        assert_eq!(checks[0].verdict, Verdict::Pass);
+        assert_eq!(checks[1].verdict, Verdict::Fail);
+        assert_eq!(checks[1].actual, Ok("241861950".to_string()));
+
+        let checks = verify_day(day, "1721\n299", &Answers::default(), timeout);
+        assert_eq!(checks[0].verdict, Verdict::Missing);
+        assert_eq!(checks[1].verdict, Verdict::Fail);
+        assert!(checks[1].actual.is_err());
+    }
+}